@@ -1,4 +1,4 @@
-use felin::mesh::Text;
+use felin::mesh::{RichText, Text, TextInput, TextInputEvent};
 use felin::prelude::*;
 use felin::{
     app, pipeline,
@@ -10,8 +10,9 @@ use winit::{dpi::LogicalSize, window::WindowBuilder};
 
 pub struct Main {
     text_pipeline: pipeline::text::Pipeline,
-    font_texture: wgpu::BindGroup,
     text_container: Text,
+    rich_text: RichText,
+    input: TextInput,
 }
 
 impl Base for Main {
@@ -26,24 +27,54 @@ impl Base for Main {
                 .with_resizable(true),
         );
 
-        FontPallet::load_font("examples/text/assets/Roboto.ttf");
+        let roboto = FontPallet::load_font("examples/text/assets/Roboto.ttf");
 
-        let mut text_pipeline = pipeline::text::Pipeline::new(system);
-        let font_texture = text_pipeline.create_font_texture(system, &FontPallet::create_font("Roboto", 32));
+        let text_pipeline = pipeline::text::Pipeline::new(system);
 
         let mut text_container = Text {
             style: Style::default(),
-            font: "Roboto".to_string(),
-            text: "Tere olen tonis !".to_string(),
+            font: roboto,
+            font_size: 32,
+            text: "Tere olen Tõnis !".to_string(),
             ..Default::default()
         };
 
         text_container.build();
 
+        let mut rich_text = RichText {
+            style: Style {
+                y: 60.0,
+                width: 400.0,
+                ..Style::default()
+            },
+            font: roboto,
+            font_size: 24,
+            ..Default::default()
+        };
+        rich_text
+            .markup("Plain, <b>bold</b>, <color=#f80>orange</color>, <size=32>big</size>, ")
+            .markup("<u>underlined</u> and <bg=#446>highlighted</bg> text in one paragraph.");
+        rich_text.build();
+
+        let mut input = TextInput {
+            style: Style {
+                y: 200.0,
+                width: 300.0,
+                height: 40.0,
+                ..Style::default()
+            },
+            placeholder: "Type here".to_string(),
+            ..Default::default()
+        };
+        input.text.font = roboto;
+        input.text.font_size = 24;
+        input.build();
+
         Main {
             text_pipeline,
-            font_texture,
             text_container,
+            rich_text,
+            input,
         }
     }
 
@@ -51,9 +82,19 @@ impl Base for Main {
         if events.resized {
             self.text_pipeline.resize(system);
         };
+
+        for event in self.input.update(events) {
+            //Submitted text replaces the heading
+            if let TextInputEvent::Submitted(value) = event {
+                self.text_container.text(&value).build();
+            }
+        }
     }
 
     fn render(&mut self, swap_chain: &mut wgpu::SwapChain, system: &mut System) {
+        self.input.blink(system.delta_time);
+        self.text_pipeline.update_glyphs(system);
+
         let frame_texture = swap_chain.get_next_texture();
         let mut encoder = system
             .device
@@ -80,7 +121,23 @@ impl Base for Main {
                 system,
                 &self.text_container.mesh().indices,
                 &self.text_container.mesh().vertices,
-                &self.font_texture,
+                self.text_pipeline.glyph_texture(),
+            );
+
+            self.text_pipeline.draw(
+                &mut pass,
+                system,
+                &self.rich_text.mesh().indices,
+                &self.rich_text.mesh().vertices,
+                self.text_pipeline.glyph_texture(),
+            );
+
+            self.text_pipeline.draw(
+                &mut pass,
+                system,
+                &self.input.mesh().indices,
+                &self.input.mesh().vertices,
+                self.text_pipeline.glyph_texture(),
             );
         }
         system.queue.submit(&[encoder.finish()]);
@@ -1,5 +1,9 @@
+use crate::pipeline::default::copy_to_layer;
+use crate::utils::texture::{mip_chain, mip_level_count, TextureOptions};
 use crate::utils::Style;
-use collision::Aabb2;
+use crate::System;
+use collision::{prelude::*, Aabb2};
+use image::{Rgba, RgbaImage};
 
 ///////////////////////////////////////////////////////////////////////////
 // Vertex
@@ -28,6 +32,25 @@ impl<T: Clone> Default for Mesh<T> {
     }
 }
 
+impl Mesh<Vertex> {
+    /// Bounding box of all vertices, None for an empty mesh
+    pub fn bounds(&self) -> Option<Aabb2<f32>> {
+        let first = self.vertices.first()?;
+        let start = cgmath::Point2::new(first.in_position[0], first.in_position[1]);
+
+        Some(
+            self.vertices
+                .iter()
+                .fold(Aabb2::new(start, start), |bounds, vertex| {
+                    bounds.grow(cgmath::Point2::new(
+                        vertex.in_position[0],
+                        vertex.in_position[1],
+                    ))
+                }),
+        )
+    }
+}
+
 impl<T: Clone> MeshTrait<T> for Mesh<T> {
     fn get_indices(&mut self) -> Vec<u16> {
         self.indices.clone()
@@ -42,14 +65,371 @@ pub trait MeshTrait<T> {
     fn get_vertices(&mut self) -> Vec<T>;
 }
 
+/// Texture array, that keeps its GPU texture and a CPU copy of every layer.
+/// Edit layers with the pixel helpers and call `flush`, or upload with `update_region`.
+/// `bind_group` changes when layers are added past capacity, read it when drawing.
 #[derive(Debug)]
 pub struct Texture {
-    pub texture_data: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    pub layers: Vec<RgbaImage>,
+    pub options: TextureOptions,
     pub texture: wgpu::Texture,
     pub bind_group: wgpu::BindGroup,
     pub name: String,
+    capacity: u32,
+    dirty: Vec<Option<(u32, u32, u32, u32)>>,
+}
+
+impl Texture {
+    pub fn new(
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+        layers: Vec<RgbaImage>,
+        options: TextureOptions,
+    ) -> Self {
+        for layer in layers.iter() {
+            assert!(
+                layer.dimensions() == (width, height),
+                "Texture array layers must have the same size, use AtlasBuilder for mixed sizes"
+            );
+        }
+
+        let capacity = layers.len().max(1) as u32;
+        let (texture, bind_group) =
+            Texture::create(system, layout, width, height, capacity, &options);
+        let dirty = vec![None; layers.len()];
+
+        let mut texture = Self {
+            width,
+            height,
+            layers,
+            options,
+            texture,
+            bind_group,
+            name: String::new(),
+            capacity,
+            dirty,
+        };
+        texture.upload_all(system);
+        texture
+    }
+
+    fn create(
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+        capacity: u32,
+        options: &TextureOptions,
+    ) -> (wgpu::Texture, wgpu::BindGroup) {
+        let mip_level_count = if options.mipmaps {
+            mip_level_count(width, height)
+        } else {
+            1
+        };
+
+        let texture = system.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: capacity,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: options.format(),
+            usage: wgpu::TextureUsage::COPY_DST
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::WRITE_ALL,
+        });
+
+        let sampler = system.device.create_sampler(&options.sampler());
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: options.format(),
+            dimension: wgpu::TextureViewDimension::D2Array,
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: 0,
+            level_count: mip_level_count,
+            base_array_layer: 0,
+            array_layer_count: capacity,
+        });
+
+        let bind_group = system.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        (texture, bind_group)
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn upload_all(&mut self, system: &System) {
+        let mut encoder = system
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        for layer in 0..self.layers.len() {
+            self.upload_layer(system, &mut encoder, layer);
+        }
+        system.queue.submit(&[encoder.finish()]);
+    }
+
+    //Whole layer with its mip chain
+    fn upload_layer(&mut self, system: &System, encoder: &mut wgpu::CommandEncoder, layer: usize) {
+        self.dirty[layer] = None;
+        let image = &self.layers[layer];
+        let levels = if self.options.mipmaps {
+            mip_chain(self.width, self.height, image.clone().into_raw())
+        } else {
+            vec![(self.width, self.height, image.clone().into_raw())]
+        };
+
+        for (level, (width, height, data)) in levels.iter().enumerate() {
+            copy_to_layer(
+                system,
+                encoder,
+                &self.texture,
+                layer as u32,
+                level as u32,
+                (0, 0, *width, *height),
+                data,
+            );
+        }
+    }
+
+    //Rectangle of the CPU copy, mipmapped textures upload the whole layer
+    fn upload_region(
+        &mut self,
+        system: &System,
+        encoder: &mut wgpu::CommandEncoder,
+        layer: usize,
+        rect: (u32, u32, u32, u32),
+    ) {
+        if self.options.mipmaps {
+            self.upload_layer(system, encoder, layer);
+            return;
+        }
+
+        self.dirty[layer] = None;
+        let (x, y, width, height) = rect;
+        let region = image::imageops::crop(&mut self.layers[layer], x, y, width, height).to_image();
+        copy_to_layer(
+            system,
+            encoder,
+            &self.texture,
+            layer as u32,
+            0,
+            rect,
+            &region.into_raw(),
+        );
+    }
+
+    /// Replace a rectangle of a layer with RGBA pixels and upload it
+    pub fn update_region(
+        &mut self,
+        system: &System,
+        layer: usize,
+        rect: (u32, u32, u32, u32),
+        pixels: &[u8],
+    ) {
+        let (x, y, width, height) = rect;
+        assert!(x + width <= self.width && y + height <= self.height);
+        let region = RgbaImage::from_raw(width, height, pixels.to_vec())
+            .expect("Region must be RGBA with 4 bytes per pixel");
+        image::imageops::replace(&mut self.layers[layer], &region, x, y);
+
+        let mut encoder = system
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        self.upload_region(system, &mut encoder, layer, rect);
+        system.queue.submit(&[encoder.finish()]);
+    }
+
+    /// Replace a whole layer, like a new video frame
+    pub fn update_layer(&mut self, system: &System, layer: usize, pixels: &[u8]) {
+        let rect = (0, 0, self.width, self.height);
+        self.update_region(system, layer, rect, pixels);
+    }
+
+    /// Add layer, returns its index for `Image.texture`.
+    /// Texture is recreated with double capacity when full, `bind_group` changes then.
+    pub fn add_layer(
+        &mut self,
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        layer: RgbaImage,
+    ) -> i32 {
+        assert!(
+            layer.dimensions() == (self.width, self.height),
+            "Texture array layers must have the same size"
+        );
+        self.layers.push(layer);
+        self.dirty.push(None);
+        let index = self.layers.len() - 1;
+
+        if self.layers.len() as u32 > self.capacity {
+            self.capacity *= 2;
+            let (texture, bind_group) = Texture::create(
+                system,
+                layout,
+                self.width,
+                self.height,
+                self.capacity,
+                &self.options,
+            );
+            self.texture = texture;
+            self.bind_group = bind_group;
+            self.upload_all(system);
+        } else {
+            let mut encoder = system
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+            self.upload_layer(system, &mut encoder, index);
+            system.queue.submit(&[encoder.finish()]);
+        }
+
+        index as i32
+    }
+
+    //Grow dirty rectangle of a layer, clipped to the texture
+    fn mark(&mut self, layer: usize, x: i64, y: i64, width: i64, height: i64) {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let (x1, y1) = (
+            (x + width).min(self.width as i64),
+            (y + height).min(self.height as i64),
+        );
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let (x0, y0, x1, y1) = (x0 as u32, y0 as u32, x1 as u32, y1 as u32);
+
+        self.dirty[layer] = Some(match self.dirty[layer] {
+            Some((dx, dy, dw, dh)) => {
+                let (nx, ny) = (dx.min(x0), dy.min(y0));
+                (nx, ny, (dx + dw).max(x1) - nx, (dy + dh).max(y1) - ny)
+            }
+            None => (x0, y0, x1 - x0, y1 - y0),
+        });
+    }
+
+    /// Upload every layer edited with the pixel helpers since the last flush
+    pub fn flush(&mut self, system: &System) {
+        if self.dirty.iter().all(|dirty| dirty.is_none()) {
+            return;
+        }
+
+        let mut encoder = system
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        for layer in 0..self.layers.len() {
+            if let Some(rect) = self.dirty[layer] {
+                self.upload_region(system, &mut encoder, layer, rect);
+            }
+        }
+        system.queue.submit(&[encoder.finish()]);
+    }
+
+    /// Mutable CPU copy of a layer, whole layer is uploaded on `flush`
+    pub fn layer_mut(&mut self, layer: usize) -> &mut RgbaImage {
+        let (width, height) = (self.width as i64, self.height as i64);
+        self.mark(layer, 0, 0, width, height);
+        &mut self.layers[layer]
+    }
+
+    pub fn pixel(&self, layer: usize, x: u32, y: u32) -> [u8; 4] {
+        self.layers[layer].get_pixel(x, y).0
+    }
+
+    /// Pixels outside the texture are ignored
+    pub fn set_pixel(&mut self, layer: usize, x: i32, y: i32, color: [u8; 4]) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        self.layers[layer].put_pixel(x as u32, y as u32, Rgba(color));
+        self.mark(layer, x as i64, y as i64, 1, 1);
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        layer: usize,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: [u8; 4],
+    ) {
+        let (x0, y0) = (x.max(0) as u32, y.max(0) as u32);
+        let x1 = ((x as i64 + width as i64).min(self.width as i64)).max(0) as u32;
+        let y1 = ((y as i64 + height as i64).min(self.height as i64)).max(0) as u32;
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.layers[layer].put_pixel(px, py, Rgba(color));
+            }
+        }
+        self.mark(layer, x as i64, y as i64, width as i64, height as i64);
+    }
+
+    pub fn clear(&mut self, layer: usize, color: [u8; 4]) {
+        let (width, height) = (self.width, self.height);
+        self.fill_rect(layer, 0, 0, width, height, color);
+    }
+
+    /// Bresenham line, `thickness` is the square brush size
+    pub fn draw_line(
+        &mut self,
+        layer: usize,
+        from: (i32, i32),
+        to: (i32, i32),
+        thickness: u32,
+        color: [u8; 4],
+    ) {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (sx, sy) = (if x < to.0 { 1 } else { -1 }, if y < to.1 { 1 } else { -1 });
+        let mut error = dx + dy;
+        let size = thickness.max(1);
+        let offset = size as i32 / 2;
+
+        loop {
+            self.fill_rect(layer, x - offset, y - offset, size, size, color);
+            if x == to.0 && y == to.1 {
+                break;
+            }
+            let double = 2 * error;
+            if double >= dy {
+                error += dy;
+                x += sx;
+            }
+            if double <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Copy an image to the layer, with its top left corner at `x`, `y`
+    pub fn blit(&mut self, layer: usize, image: &RgbaImage, x: u32, y: u32) {
+        image::imageops::replace(&mut self.layers[layer], image, x, y);
+        let (width, height) = image.dimensions();
+        self.mark(layer, x as i64, y as i64, width as i64, height as i64);
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -78,3 +458,8 @@ pub trait ElementCollider {
     fn contains(&self, point: cgmath::Point2<f32>) -> bool;
     fn get_collider(&self) -> Aabb2<f32>;
 }
+
+/// Element with a collider, what `Grid` holds as children
+pub trait Element: ElementCore + ElementCollider {}
+
+impl<T: ElementCore + ElementCollider> Element for T {}
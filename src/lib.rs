@@ -7,7 +7,7 @@ pub mod utils;
 
 pub use system::System;
 
-use crate::utils::Event;
+use crate::utils::{AssetManager, Event};
 
 use winit::{
     event::{self, WindowEvent},
@@ -59,6 +59,8 @@ pub fn app<E: Base>() {
         window: WindowBuilder::new()
             .with_title("title")
             .with_resizable(true),
+        delta_time: 0.0,
+        assets: AssetManager::default(),
     };
 
     let mut example = E::init(&mut system);
@@ -76,6 +78,7 @@ pub fn app<E: Base>() {
         .device
         .create_swap_chain(&surface, &system.screen_descriptor);
     let mut input_events = Event::new();
+    let mut last_frame = std::time::Instant::now();
 
     ///////////////////////////////////////////////////////////////////////////
     // Render loop
@@ -112,6 +115,10 @@ pub fn app<E: Base>() {
                 }
             },
             event::Event::EventsCleared => {
+                let now = std::time::Instant::now();
+                system.delta_time = now.duration_since(last_frame).as_secs_f32();
+                last_frame = now;
+                system.poll_assets();
                 example.render(&mut swap_chain, &mut system);
             }
             _ => (),
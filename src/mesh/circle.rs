@@ -1,5 +1,6 @@
 use crate::definitions::{Mesh, Vertex};
-use crate::utils::Style;
+use crate::utils::collider::Shape;
+use crate::utils::{Collider, Gradient, Style};
 
 use lyon::math::*;
 use lyon::tessellation;
@@ -7,8 +8,7 @@ use lyon::tessellation::basic_shapes::*;
 use lyon::tessellation::geometry_builder::{BuffersBuilder, VertexBuffers};
 use lyon::tessellation::FillOptions;
 
-use cgmath::{self, prelude::*};
-use collision::{prelude::*, primitive, Aabb2};
+use collision::Aabb2;
 
 use crate::prelude::*;
 
@@ -16,8 +16,9 @@ use crate::prelude::*;
 #[derive(Clone)]
 pub struct Circle {
     pub style: Style,
-    pub collider: Aabb2<f32>,
+    pub collider: Collider,
     pub color: [f32; 4],
+    pub gradient: Option<Gradient>,
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u16>,
     pub id: Option<String>,
@@ -27,11 +28,9 @@ impl Default for Circle {
     fn default() -> Self {
         Self {
             style: Style::default(),
-            collider: Aabb2 {
-                min: cgmath::Point2::new(0.0, 0.0),
-                max: cgmath::Point2::new(0.0, 0.0),
-            },
+            collider: Collider::default(),
             color: [1.0, 1.0, 1.0, 1.0],
+            gradient: None,
             vertices: Vec::new(),
             indices: Vec::new(),
             id: None,
@@ -47,7 +46,7 @@ impl ElementCore for Circle {
         let fill_options = FillOptions::tolerance(0.01);
         //Draw vertices with Lyon
         fill_circle(
-            point(self.style.x, self.style.x),
+            point(self.style.x, self.style.y),
             self.style.radius,
             &fill_options,
             &mut BuffersBuilder::new(&mut mesh, |vertex: tessellation::FillVertex| Vertex {
@@ -59,7 +58,16 @@ impl ElementCore for Circle {
         )
         .unwrap();
 
-        self.collider = self.get_collider();
+        let bounds = self.bounds();
+        if let Some(gradient) = &self.gradient {
+            gradient.fill(bounds, &mut mesh.vertices, &mut mesh.indices);
+        }
+        self.style.transform.apply(&bounds, &mut mesh.vertices);
+
+        self.collider = Collider::new(bounds, &self.style.transform).with_shape(Shape::Circle {
+            center: cgmath::Point2::new(self.style.x, self.style.y),
+            radius: self.style.radius,
+        });
         self.vertices = mesh.vertices;
         self.indices = mesh.indices;
     }
@@ -84,20 +92,28 @@ impl ElementCore for Circle {
     }
 }
 
+impl Circle {
+    //Untransformed circle bounding box
+    fn bounds(&self) -> Aabb2<f32> {
+        Aabb2::new(
+            cgmath::Point2::new(
+                self.style.x - self.style.radius,
+                self.style.y - self.style.radius,
+            ),
+            cgmath::Point2::new(
+                self.style.x + self.style.radius,
+                self.style.y + self.style.radius,
+            ),
+        )
+    }
+}
+
 impl ElementCollider for Circle {
     fn contains(&self, point: cgmath::Point2<f32>) -> bool {
-        self.collider.contains(&point)
+        self.collider.contains(point)
     }
 
     fn get_collider(&self) -> Aabb2<f32> {
-        let transform: cgmath::Decomposed<cgmath::Vector2<f32>, cgmath::Basis2<f32>> =
-            cgmath::Decomposed {
-                scale: 1.0,
-                rot: Rotation2::from_angle(cgmath::Rad(0.0)),
-                disp: cgmath::Vector2::new(self.style.x, self.style.y),
-            };
-        return primitive::Circle::new(self.style.radius)
-            .compute_bound()
-            .transform(&transform);
+        Collider::new(self.bounds(), &self.style.transform).bounds
     }
 }
@@ -1,10 +1,15 @@
 use crate::definitions::{Mesh, Vertex};
 use crate::prelude::*;
-use crate::utils::{Batch, Style};
+use crate::utils::{Batch, Collider, CullStats, Style};
+use collision::Aabb2;
 
 pub struct Grid<'a, 'b> {
     pub style: Style,
-    pub children: &'a mut Vec<&'b mut dyn ElementCore<Vertex = Vertex>>,
+    pub children: &'a mut Vec<&'b mut dyn Element<Vertex = Vertex>>,
+}
+
+fn overlaps(a: &Aabb2<f32>, b: &Aabb2<f32>) -> bool {
+    a.min.x <= b.max.x && a.max.x >= b.min.x && a.min.y <= b.max.y && a.max.y >= b.min.y
 }
 
 #[allow(dead_code)]
@@ -26,22 +31,55 @@ impl<'a, 'b> ElementCore for Grid<'a, 'b> {
     fn build(&mut self) {}
 
     fn mesh(&mut self) -> Mesh<Vertex> {
+        self.batch(None).0
+    }
+}
+
+impl<'a, 'b> Grid<'a, 'b> {
+    //Place children in their cells and batch them, children outside of `view` are not built
+    fn batch(&mut self, view: Option<&Aabb2<f32>>) -> (Mesh<Vertex>, CullStats) {
         let mut batch: Batch<Vertex> = Batch::new();
         for child in self.children.iter_mut() {
             let style = Style::calculate_style(self.style, child.get_style());
             child.set_style(style);
+
+            let view = match view {
+                Some(view) => view,
+                None => {
+                    child.build();
+                    batch.add(&mut child.mesh());
+                    continue;
+                }
+            };
+
+            //Colliders are only up to date after a build, the style box is known before it.
+            //Boxes without a size, like text without a width, are built and checked by mesh.
+            let sized = style.width > 0.0 && style.height > 0.0;
+            let area = Collider::new(style.bounds(), &style.transform).bounds;
+            if sized && !overlaps(&area, view) {
+                batch.stats.culled += 1;
+                continue;
+            }
+
             child.build();
-            batch.add(&mut child.mesh())
+            batch.add_visible(&mut child.mesh(), view);
         }
 
-        Mesh {
-            vertices: batch.vertices,
-            indices: batch.indices,
-        }
+        (
+            Mesh {
+                vertices: batch.vertices,
+                indices: batch.indices,
+            },
+            batch.stats,
+        )
+    }
+
+    /// Like `mesh`, but children outside of `view` are not built or added,
+    /// for example `system.screen_rect()`.
+    pub fn mesh_in_view(&mut self, view: &Aabb2<f32>) -> (Mesh<Vertex>, CullStats) {
+        self.batch(Some(view))
     }
-}
 
-impl<'a, 'b> Grid<'a, 'b> {
     pub fn finish(&mut self) -> &mut Self {
         for child in self.children.iter_mut() {
             let style = Style::calculate_style(self.style, child.get_style());
@@ -53,3 +91,193 @@ impl<'a, 'b> Grid<'a, 'b> {
         self
     }
 }
+
+impl<'a, 'b> ElementCollider for Grid<'a, 'b> {
+    fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+        Collider::new(self.style.bounds(), &self.style.transform).contains(point)
+    }
+
+    fn get_collider(&self) -> Aabb2<f32> {
+        Collider::new(self.style.bounds(), &self.style.transform).bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::text::Text;
+    use crate::utils::font::load_font_bytes;
+    use crate::utils::Transform;
+
+    //Quad filling its style box, counts builds
+    struct Probe {
+        style: Style,
+        builds: usize,
+    }
+
+    impl Probe {
+        fn new(column_start: usize, column_end: usize) -> Self {
+            Self {
+                style: Style {
+                    row_start: 0,
+                    row_end: 1,
+                    column_start,
+                    column_end,
+                    ..Style::default()
+                },
+                builds: 0,
+            }
+        }
+    }
+
+    impl ElementCore for Probe {
+        type Vertex = Vertex;
+
+        fn build(&mut self) {
+            self.builds += 1;
+        }
+
+        fn get_style(&self) -> Style {
+            self.style
+        }
+
+        fn get_id(&self) -> Option<String> {
+            None
+        }
+
+        fn set_style(&mut self, style: Style) {
+            self.style = style;
+        }
+
+        fn mesh(&mut self) -> Mesh<Vertex> {
+            let bounds = self.style.bounds();
+            let vertex = |x: f32, y: f32| Vertex {
+                in_position: [x, y],
+                in_color: [1.0; 4],
+                tex_pos: [0.0, 0.0],
+                texture_id: -1,
+            };
+            Mesh {
+                vertices: vec![
+                    vertex(bounds.min.x, bounds.min.y),
+                    vertex(bounds.max.x, bounds.min.y),
+                    vertex(bounds.max.x, bounds.max.y),
+                    vertex(bounds.min.x, bounds.max.y),
+                ],
+                indices: vec![0, 1, 2, 2, 3, 0],
+            }
+        }
+    }
+
+    impl ElementCollider for Probe {
+        fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+            Collider::new(self.style.bounds(), &Transform::default()).contains(point)
+        }
+
+        fn get_collider(&self) -> Aabb2<f32> {
+            Collider::new(self.style.bounds(), &Transform::default()).bounds
+        }
+    }
+
+    #[test]
+    fn culled_children_are_not_built() {
+        let (mut left, mut middle, mut right) =
+            (Probe::new(0, 1), Probe::new(4, 5), Probe::new(9, 10));
+        let view = Aabb2::new(
+            cgmath::Point2::new(0.0, 0.0),
+            cgmath::Point2::new(500.0, 100.0),
+        );
+
+        let (mesh, stats) = Grid {
+            style: Style {
+                width: 1000.0,
+                height: 100.0,
+                rows: 1,
+                columns: 10,
+                ..Style::default()
+            },
+            children: &mut vec![&mut left, &mut middle, &mut right],
+        }
+        .mesh_in_view(&view);
+
+        assert_eq!(
+            stats,
+            CullStats {
+                drawn: 2,
+                culled: 1
+            }
+        );
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!((left.builds, middle.builds, right.builds), (1, 1, 0));
+    }
+
+    #[test]
+    fn mesh_builds_every_child() {
+        let (mut left, mut right) = (Probe::new(0, 1), Probe::new(9, 10));
+
+        let mesh = Grid {
+            style: Style {
+                width: 1000.0,
+                height: 100.0,
+                rows: 1,
+                columns: 10,
+                ..Style::default()
+            },
+            children: &mut vec![&mut left, &mut right],
+        }
+        .mesh();
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!((left.builds, right.builds), (1, 1));
+    }
+
+    #[test]
+    fn text_moved_into_view_is_built() {
+        let mut text = Text {
+            font: load_font_bytes(include_bytes!("../../examples/text/assets/Roboto.ttf")),
+            style: Style {
+                row_start: 0,
+                row_end: 1,
+                column_start: 9,
+                column_end: 10,
+                ..Style::default()
+            },
+            ..Text::default()
+        };
+        text.text("Hello");
+        let style = Style {
+            width: 1000.0,
+            height: 100.0,
+            rows: 1,
+            columns: 10,
+            ..Style::default()
+        };
+        let view = Aabb2::new(
+            cgmath::Point2::new(0.0, 0.0),
+            cgmath::Point2::new(500.0, 100.0),
+        );
+
+        let (mesh, _) = Grid {
+            style,
+            children: &mut vec![&mut text],
+        }
+        .mesh_in_view(&view);
+        assert!(mesh.vertices.is_empty());
+
+        text.style.column_start = 0;
+        text.style.column_end = 1;
+        let (mesh, stats) = Grid {
+            style,
+            children: &mut vec![&mut text],
+        }
+        .mesh_in_view(&view);
+        assert!(!mesh.vertices.is_empty());
+        assert_eq!(
+            stats,
+            CullStats {
+                drawn: 1,
+                culled: 0
+            }
+        );
+    }
+}
@@ -1,16 +1,120 @@
 use crate::definitions::{Mesh, Vertex};
 use crate::prelude::*;
-use crate::utils::Style;
-use collision::{prelude::*, primitive, Aabb2};
+use crate::utils::sprite::{Frame, UvRect};
+use crate::utils::{Batch, Collider, Style};
+use collision::Aabb2;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SliceMode {
+    Stretch,
+    Tile,
+}
+
+/// Nine-slice insets in texels, corners keep their size while edges and center stretch or tile.
+#[derive(Copy, Clone, Debug)]
+pub struct NineSlice {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+    /// Texture array layer size in texels
+    pub texture_width: f32,
+    pub texture_height: f32,
+    pub mode: SliceMode,
+}
+
+impl Default for NineSlice {
+    fn default() -> Self {
+        Self {
+            left: 0.0,
+            right: 0.0,
+            top: 0.0,
+            bottom: 0.0,
+            texture_width: 1.0,
+            texture_height: 1.0,
+            mode: SliceMode::Stretch,
+        }
+    }
+}
+
+//Smallest tile in pixels and most tiles per axis, so tiled images fit in u16 indices
+const MIN_TILE_SIZE: f32 = 1.0;
+const MAX_TILES: usize = 64;
+
+//Part of one axis: screen start, screen end, texture start, texture end
+type Segment = (f32, f32, f32, f32);
+
+//Split one axis to start, middle and end parts
+fn slice_axis(
+    start: f32,
+    size: f32,
+    insets: (f32, f32),
+    uv: (f32, f32),
+    texture_size: f32,
+    mode: SliceMode,
+) -> [Vec<Segment>; 3] {
+    //Texture coordinates go backwards for flipped images
+    let texel = (uv.1 - uv.0).signum() / texture_size;
+
+    //Shrink corners, when the element is smaller than both insets
+    let scale = if insets.0 + insets.1 > size && insets.0 + insets.1 > 0.0 {
+        size / (insets.0 + insets.1)
+    } else {
+        1.0
+    };
+
+    let positions = [
+        start,
+        start + insets.0 * scale,
+        start + size - insets.1 * scale,
+        start + size,
+    ];
+    let coordinates = [uv.0, uv.0 + insets.0 * texel, uv.1 - insets.1 * texel, uv.1];
+
+    //Tiles are one texel per pixel, tiny tiles or too many of them are stretched instead
+    let middle_texels = (coordinates[2] - coordinates[1]) / texel;
+    let tile_count = ((positions[2] - positions[1]) / middle_texels).ceil();
+    let tiled =
+        mode == SliceMode::Tile && middle_texels >= MIN_TILE_SIZE && tile_count <= MAX_TILES as f32;
+    let middle = if tiled {
+        (0..tile_count as usize)
+            .map(|tile| {
+                let position = positions[1] + middle_texels * tile as f32;
+                let end = (position + middle_texels).min(positions[2]);
+                let fraction = (end - position) / middle_texels;
+                (
+                    position,
+                    end,
+                    coordinates[1],
+                    coordinates[1] + (coordinates[2] - coordinates[1]) * fraction,
+                )
+            })
+            .collect()
+    } else {
+        vec![(positions[1], positions[2], coordinates[1], coordinates[2])]
+    };
+
+    [
+        vec![(positions[0], positions[1], coordinates[0], coordinates[1])],
+        middle,
+        vec![(positions[2], positions[3], coordinates[2], coordinates[3])],
+    ]
+}
 
 #[derive(Clone)]
 pub struct Image {
     pub style: Style,
-    pub collider: Aabb2<f32>,
+    pub collider: Collider,
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u16>,
     pub color: [f32; 4],
     pub texture: i32,
+    pub uv: UvRect,
+    /// Part of the box the texture covers, less than the whole box for trimmed sprite frames
+    pub trim: UvRect,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub nine_slice: Option<NineSlice>,
     pub id: Option<String>,
 }
 
@@ -19,13 +123,15 @@ impl Default for Image {
         Self {
             style: Style::default(),
             color: [0.0, 0.0, 0.0, 0.0],
-            collider: Aabb2 {
-                min: cgmath::Point2::new(0.0, 0.0),
-                max: cgmath::Point2::new(0.0, 0.0),
-            },
+            collider: Collider::default(),
             vertices: Vec::new(),
             indices: Vec::new(),
             texture: 0,
+            uv: UvRect::default(),
+            trim: UvRect::default(),
+            flip_x: false,
+            flip_y: false,
+            nine_slice: None,
             id: None,
         }
     }
@@ -35,82 +141,166 @@ impl Default for Image {
 impl ElementCore for Image {
     type Vertex = Vertex;
     fn build(&mut self) {
+        //Shadow goes first, so it is drawn behind the image
+        let mut batch: Batch<Vertex> = Batch::new();
+        if let Some(shadow) = self.style.box_shadow {
+            batch.add(&mut shadow.mesh(
+                self.style.x,
+                self.style.y,
+                self.style.width,
+                self.style.height,
+                0.0,
+            ));
+        }
+
+        //Trimmed area moves to the other side with a flip
+        let (x, y) = (
+            if self.flip_x {
+                1.0 - self.trim.x - self.trim.width
+            } else {
+                self.trim.x
+            },
+            if self.flip_y {
+                1.0 - self.trim.y - self.trim.height
+            } else {
+                self.trim.y
+            },
+        );
+        let (x, y) = (
+            self.style.x + x * self.style.width,
+            self.style.y + y * self.style.height,
+        );
+        let (width, height) = (
+            self.trim.width * self.style.width,
+            self.trim.height * self.style.height,
+        );
+
+        let (u, v) = (self.uv.u_range(self.flip_x), self.uv.v_range(self.flip_y));
+        match self.nine_slice {
+            Some(slice) => {
+                //Flipped texture shows the opposite inset first
+                let columns = slice_axis(
+                    x,
+                    width,
+                    if self.flip_x {
+                        (slice.right, slice.left)
+                    } else {
+                        (slice.left, slice.right)
+                    },
+                    u,
+                    slice.texture_width,
+                    slice.mode,
+                );
+                let rows = slice_axis(
+                    y,
+                    height,
+                    if self.flip_y {
+                        (slice.bottom, slice.top)
+                    } else {
+                        (slice.top, slice.bottom)
+                    },
+                    v,
+                    slice.texture_height,
+                    slice.mode,
+                );
+
+                for row in rows.iter() {
+                    for column in columns.iter() {
+                        for y in row.iter() {
+                            for x in column.iter() {
+                                batch.add(&mut self.quad(*x, *y));
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                batch.add(&mut self.quad((x, x + width, u.0, u.1), (y, y + height, v.0, v.1)));
+            }
+        }
+
+        let bounds = self.style.bounds();
+        self.style.transform.apply(&bounds, &mut batch.vertices);
+
+        self.indices = batch.indices;
+        self.vertices = batch.vertices;
+        self.collider = Collider::new(bounds, &self.style.transform);
+    }
+
+    fn get_style(&self) -> Style {
+        self.style
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn get_id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    fn mesh(&mut self) -> Mesh<Vertex> {
+        Mesh {
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl Image {
+    /// Show a sprite sheet frame
+    pub fn set_frame(&mut self, frame: &Frame) -> &mut Self {
+        self.texture = frame.layer;
+        self.uv = frame.uv;
+        self.trim = frame.trim;
+        self
+    }
+
+    fn quad(&self, x: Segment, y: Segment) -> Mesh<Vertex> {
         let vertices = vec![
             //Left top corner
             Vertex {
-                in_position: [self.style.x, self.style.y],
+                in_position: [x.0, y.0],
                 in_color: self.color,
-                tex_pos: [0.0, 0.0],
+                tex_pos: [x.2, y.2],
                 texture_id: self.texture,
             },
             //Right top corner
             Vertex {
-                in_position: [self.style.x + self.style.width, self.style.y],
+                in_position: [x.1, y.0],
                 in_color: self.color,
-                tex_pos: [1.0, 0.0],
+                tex_pos: [x.3, y.2],
                 texture_id: self.texture,
             },
             //Right bottom corner
             Vertex {
-                in_position: [
-                    self.style.x + self.style.width,
-                    self.style.y + self.style.height,
-                ],
+                in_position: [x.1, y.1],
                 in_color: self.color,
-                tex_pos: [1.0, 1.0],
+                tex_pos: [x.3, y.3],
                 texture_id: self.texture,
             },
             //Left bottom
             Vertex {
-                in_position: [self.style.x, self.style.y + self.style.height],
+                in_position: [x.0, y.1],
                 in_color: self.color,
-                tex_pos: [0.0, 1.0],
+                tex_pos: [x.2, y.3],
                 texture_id: self.texture,
             },
         ];
 
-        self.indices = vec![0, 1, 2, 2, 3, 0];
-        self.vertices = vertices;
-        self.collider = self.get_collider();
-    }
-
-    fn get_style(&self) -> Style {
-        self.style
-    }
-
-    fn set_style(&mut self, style: Style) {
-        self.style = style;
-    }
-
-    fn get_id(&self) -> Option<String> {
-        self.id.clone()
-    }
-
-    fn mesh(&mut self) -> Mesh<Vertex> {
         Mesh {
-            vertices: self.vertices.clone(),
-            indices: self.indices.clone(),
+            vertices,
+            indices: vec![0, 1, 2, 2, 3, 0],
         }
     }
 }
 
 impl ElementCollider for Image {
     fn get_collider(&self) -> Aabb2<f32> {
-        let transform: cgmath::Decomposed<cgmath::Vector2<f32>, cgmath::Basis2<f32>> =
-            cgmath::Decomposed {
-                scale: 1.0,
-                rot: cgmath::Rotation2::from_angle(cgmath::Rad(0.0)),
-                disp: cgmath::Vector2::new(
-                    self.style.x + (self.style.width / 2.0),
-                    self.style.y + (self.style.height / 2.0),
-                ),
-            };
-        primitive::Rectangle::new(self.style.width, self.style.height)
-            .compute_bound()
-            .transform(&transform)
+        Collider::new(self.style.bounds(), &self.style.transform).bounds
     }
 
     fn contains(&self, point: cgmath::Point2<f32>) -> bool {
-        self.collider.contains(&point)
+        self.collider.contains(point)
     }
 }
@@ -2,10 +2,14 @@ pub mod circle;
 pub mod grid;
 pub mod img;
 pub mod rectangle;
+pub mod rich_text;
 pub mod text;
+pub mod text_input;
 
 pub use circle::Circle;
 pub use grid::Grid;
-pub use img::Image;
+pub use img::{Image, NineSlice, SliceMode};
 pub use rectangle::Rectangle;
+pub use rich_text::{RichText, Span, SpanStyle};
 pub use text::Text;
+pub use text_input::{TextInput, TextInputEvent};
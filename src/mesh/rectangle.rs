@@ -6,9 +6,10 @@ use lyon::tessellation::basic_shapes::*;
 use lyon::tessellation::geometry_builder::{BuffersBuilder, VertexBuffers};
 use lyon::tessellation::FillOptions;
 
-use collision::{prelude::*, primitive, Aabb2};
+use collision::Aabb2;
 
-use crate::utils::Style;
+use crate::utils::collider::Shape;
+use crate::utils::{Batch, Collider, Gradient, Style};
 
 use crate::prelude::*;
 
@@ -16,7 +17,8 @@ use crate::prelude::*;
 pub struct Rectangle {
     pub style: Style,
     pub color: [f32; 4],
-    pub collider: Aabb2<f32>,
+    pub gradient: Option<Gradient>,
+    pub collider: Collider,
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u16>,
     pub id: Option<String>,
@@ -26,13 +28,11 @@ impl Default for Rectangle {
     fn default() -> Self {
         Self {
             style: Style::default(),
-            collider: Aabb2 {
-                min: cgmath::Point2::new(0.0, 0.0),
-                max: cgmath::Point2::new(0.0, 0.0),
-            },
+            collider: Collider::default(),
             vertices: Vec::new(),
             indices: Vec::new(),
             color: [1.0, 1.0, 1.0, 1.0],
+            gradient: None,
             id: None,
         }
     }
@@ -45,27 +45,66 @@ impl ElementCore for Rectangle {
         let mut mesh: VertexBuffers<Vertex, u16> = VertexBuffers::new();
         let fill_options = FillOptions::tolerance(0.01);
 
-        //Draw vertices with Lyon
-        fill_rectangle(
-            &rect(
-                self.style.x,
-                self.style.y,
-                self.style.width,
-                self.style.height,
-            ),
-            &fill_options,
-            &mut BuffersBuilder::new(&mut mesh, |vertex: tessellation::FillVertex| Vertex {
+        let shape = rect(
+            self.style.x,
+            self.style.y,
+            self.style.width,
+            self.style.height,
+        );
+        let mut builder =
+            BuffersBuilder::new(&mut mesh, |vertex: tessellation::FillVertex| Vertex {
                 in_position: vertex.position.to_array(),
                 in_color: self.color,
                 tex_pos: [0.0, 0.0],
                 texture_id: -1,
-            }),
-        )
-        .unwrap();
+            });
+
+        //Draw vertices with Lyon
+        if self.style.radius > 0.0 {
+            fill_rounded_rectangle(
+                &shape,
+                &BorderRadii::new_all_same(self.style.radius),
+                &fill_options,
+                &mut builder,
+            )
+            .unwrap();
+        } else {
+            fill_rectangle(&shape, &fill_options, &mut builder).unwrap();
+        }
 
-        self.vertices = mesh.vertices;
-        self.indices = mesh.indices;
-        self.collider = self.get_collider();
+        let bounds = self.style.bounds();
+        if let Some(gradient) = &self.gradient {
+            gradient.fill(bounds, &mut mesh.vertices, &mut mesh.indices);
+        }
+
+        //Shadow goes first, so it is drawn behind the rectangle
+        let mut batch: Batch<Vertex> = Batch::new();
+        if let Some(shadow) = self.style.box_shadow {
+            batch.add(&mut shadow.mesh(
+                self.style.x,
+                self.style.y,
+                self.style.width,
+                self.style.height,
+                self.style.radius,
+            ));
+        }
+        batch.add(&mut Mesh {
+            vertices: mesh.vertices,
+            indices: mesh.indices,
+        });
+
+        self.style.transform.apply(&bounds, &mut batch.vertices);
+
+        self.vertices = batch.vertices;
+        self.indices = batch.indices;
+        let shape = if self.style.radius > 0.0 {
+            Shape::RoundedRectangle {
+                radius: self.style.radius,
+            }
+        } else {
+            Shape::Rectangle
+        };
+        self.collider = Collider::new(bounds, &self.style.transform).with_shape(shape);
     }
 
     fn get_style(&self) -> Style {
@@ -96,26 +135,17 @@ impl ElememtResizable for Rectangle {
         self.style.height = height;
     }
 
-    fn radius(&mut self, _radius: f32) {}
+    fn radius(&mut self, radius: f32) {
+        self.style.radius = radius;
+    }
 }
 
 impl ElementCollider for Rectangle {
     fn contains(&self, point: cgmath::Point2<f32>) -> bool {
-        self.collider.contains(&point)
+        self.collider.contains(point)
     }
 
     fn get_collider(&self) -> Aabb2<f32> {
-        let transform: cgmath::Decomposed<cgmath::Vector2<f32>, cgmath::Basis2<f32>> =
-            cgmath::Decomposed {
-                scale: 1.0,
-                rot: cgmath::Rotation2::from_angle(cgmath::Rad(0.0)),
-                disp: cgmath::Vector2::new(
-                    self.style.x + (self.style.width / 2.0),
-                    self.style.y + (self.style.height / 2.0),
-                ),
-            };
-        primitive::Rectangle::new(self.style.width, self.style.height)
-            .compute_bound()
-            .transform(&transform)
+        Collider::new(self.style.bounds(), &self.style.transform).bounds
     }
 }
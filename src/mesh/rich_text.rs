@@ -0,0 +1,430 @@
+use crate::definitions::{Mesh, Vertex};
+use crate::mesh::text::{glyph_mesh, line_boxes, solid_mesh};
+use crate::prelude::*;
+use crate::utils::font::{cache_runs, font_id, glyph_generation, FontId, FontPallet};
+use crate::utils::layout::{
+    layout, LayoutOptions, Overflow, TextAlign, TextLayout, TextRun, VerticalAlign, WhiteSpace,
+};
+use crate::utils::{Batch, Collider, Style};
+use collision::Aabb2;
+
+/// Look of one span, `None` uses the value of the `RichText`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SpanStyle {
+    pub font: Option<FontId>,
+    pub size: Option<i32>,
+    pub color: Option<[f32; 4]>,
+    /// Uses `RichText::bold_font`, without it glyphs are drawn twice with an offset
+    pub bold: bool,
+    pub underline: bool,
+    pub background: Option<[f32; 4]>,
+}
+
+impl SpanStyle {
+    pub fn font(mut self, font: FontId) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn size(mut self, size: i32) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn color(mut self, color: [f32; 4]) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn background(mut self, color: [f32; 4]) -> Self {
+        self.background = Some(color);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+/// Color from `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`
+pub fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.trim().trim_start_matches('#');
+    let digits: Vec<u32> = hex
+        .chars()
+        .map(|ch| ch.to_digit(16))
+        .collect::<Option<Vec<u32>>>()?;
+
+    let channels: Vec<f32> = match digits.len() {
+        3 | 4 => digits.iter().map(|d| (d * 17) as f32 / 255.0).collect(),
+        6 | 8 => digits
+            .chunks(2)
+            .map(|pair| (pair[0] * 16 + pair[1]) as f32 / 255.0)
+            .collect(),
+        _ => return None,
+    };
+
+    Some([
+        channels[0],
+        channels[1],
+        channels[2],
+        channels.get(3).cloned().unwrap_or(1.0),
+    ])
+}
+
+//Style with one markup tag applied, None for unknown tags
+fn apply_tag(style: SpanStyle, name: &str, value: Option<&str>) -> Option<SpanStyle> {
+    match (name, value) {
+        ("b", None) => Some(style.bold()),
+        ("u", None) => Some(style.underline()),
+        ("color", Some(value)) => parse_color(value).map(|color| style.color(color)),
+        ("bg", Some(value)) | ("mark", Some(value)) => {
+            parse_color(value).map(|color| style.background(color))
+        }
+        ("size", Some(value)) => value.trim().parse().ok().map(|size| style.size(size)),
+        ("font", Some(value)) => font_id(value.trim()).map(|font| style.font(font)),
+        _ => None,
+    }
+}
+
+/// Split markup to spans. Tags are `<b>`, `<u>`, `<color=#f00>`, `<bg=#ff0>`,
+/// `<size=20>` and `<font=Name>` for fonts registered with `register_font_name`,
+/// closed by `</b>`, `</color>` and so on. Unknown tags stay in the text.
+pub fn parse_markup(markup: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    //Open tags with the style before them
+    let mut stack: Vec<(String, SpanStyle)> = Vec::new();
+    let mut style = SpanStyle::default();
+    let mut text = String::new();
+
+    let mut rest = markup;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let close = match rest.find('>') {
+            Some(close) => close,
+            None => break,
+        };
+        let tag = &rest[1..close];
+
+        let new_style = if tag.starts_with('/') {
+            let name = tag[1..].trim();
+            stack
+                .iter()
+                .rposition(|(open, _)| open == name)
+                .map(|index| {
+                    let previous = stack[index].1;
+                    stack.truncate(index);
+                    previous
+                })
+        } else {
+            let mut parts = tag.splitn(2, '=');
+            let name = parts.next().unwrap_or("").trim();
+            apply_tag(style, name, parts.next()).map(|new_style| {
+                stack.push((name.to_string(), style));
+                new_style
+            })
+        };
+
+        match new_style {
+            Some(new_style) => {
+                if !text.is_empty() {
+                    spans.push(Span {
+                        text: std::mem::replace(&mut text, String::new()),
+                        style,
+                    });
+                }
+                style = new_style;
+                rest = &rest[close + 1..];
+            }
+            None => {
+                text.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+
+    if !text.is_empty() {
+        spans.push(Span { text, style });
+    }
+    spans
+}
+
+/// Paragraph of styled spans, wrapped together like one `Text`
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct RichText {
+    pub style: Style,
+    pub spans: Vec<Span>,
+    pub font: FontId,
+    /// Font for `<b>` spans
+    pub bold_font: Option<FontId>,
+    pub font_size: i32,
+    /// Distance field glyphs, draw with `Pipeline::draw_sdf`
+    pub sdf: bool,
+    pub color: [f32; 4],
+    pub line_height: f32,
+    pub white_space: WhiteSpace,
+    pub overflow: Overflow,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    pub max_lines: Option<usize>,
+    /// Glyph positions from the last build, glyph `run` is the span index
+    pub layout: TextLayout,
+
+    pub glyph_generation: u32,
+    pub collider: Collider,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub id: Option<String>,
+}
+
+impl Default for RichText {
+    fn default() -> Self {
+        Self {
+            style: Style::default(),
+            spans: Vec::new(),
+            font: FontId::default(),
+            bold_font: None,
+            font_size: 32,
+            sdf: false,
+            color: [1.0, 1.0, 1.0, 1.0],
+            line_height: 1.0,
+            white_space: WhiteSpace::Normal,
+            overflow: Overflow::Visible,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            max_lines: None,
+            layout: TextLayout::default(),
+            glyph_generation: 0,
+            collider: Collider::default(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            id: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl ElementCore for RichText {
+    type Vertex = Vertex;
+    fn build(&mut self) {
+        let runs: Vec<TextRun> = self
+            .spans
+            .iter()
+            .map(|span| TextRun {
+                text: &span.text,
+                font: self.span_font(&span.style),
+            })
+            .collect();
+
+        //Glyphs of all spans are cached as one set, like in `Text::build`
+        cache_runs(&runs);
+        self.glyph_generation = glyph_generation();
+        let text_layout = layout(&runs, &self.layout_options());
+
+        //Backgrounds go under the glyphs and underlines over them
+        let mut backgrounds: Batch<Vertex> = Batch::new();
+        let mut glyphs: Batch<Vertex> = Batch::new();
+        let mut underlines: Batch<Vertex> = Batch::new();
+
+        for glyph in text_layout.glyphs.iter().filter(|glyph| glyph.is_visible()) {
+            let style = &self.spans[glyph.run].style;
+            let color = style.color.unwrap_or(self.color);
+            let (left, top) = glyph.top_left();
+            let (x, y) = (self.style.x + left, self.style.y + top);
+            glyphs.add(&mut glyph_mesh(x, y, &glyph.glyph, color));
+
+            if style.bold && self.bold_font.is_none() {
+                let offset = (runs[glyph.run].font.size as f32 / 24.0).max(1.0);
+                glyphs.add(&mut glyph_mesh(x + offset, y, &glyph.glyph, color));
+            }
+        }
+
+        for line in text_layout.lines.iter() {
+            let right = line.x + line.width;
+            let mut index = line.start;
+            while index < line.end {
+                let run = text_layout.glyphs[index].run;
+                let start = text_layout.glyphs[index].x;
+                let mut end = start;
+                while index < line.end && text_layout.glyphs[index].run == run {
+                    let glyph = &text_layout.glyphs[index];
+                    end = glyph.x + glyph.glyph.advance;
+                    index += 1;
+                }
+                //Trailing spaces of the line are not decorated
+                let width = end.min(right) - start;
+                if width <= 0.0 {
+                    continue;
+                }
+
+                let x = self.style.x + start;
+                let style = &self.spans[run].style;
+                if let Some(background) = style.background {
+                    backgrounds.add(&mut solid_mesh(
+                        x,
+                        self.style.y + line.top,
+                        width,
+                        line.height,
+                        background,
+                    ));
+                }
+                if style.underline {
+                    let size = runs[run].font.size as f32;
+                    let thickness = (size / 16.0).max(1.0);
+                    underlines.add(&mut solid_mesh(
+                        x,
+                        self.style.y + line.baseline + size * 0.1,
+                        width,
+                        thickness,
+                        style.color.unwrap_or(self.color),
+                    ));
+                }
+            }
+        }
+
+        self.layout = text_layout;
+
+        let mut batch = backgrounds;
+        batch.add(&mut Mesh {
+            vertices: glyphs.vertices,
+            indices: glyphs.indices,
+        });
+        batch.add(&mut Mesh {
+            vertices: underlines.vertices,
+            indices: underlines.indices,
+        });
+
+        //Line boxes, so clicks between glyphs hit the text
+        let bounds = self.style.bounds();
+        let lines = line_boxes(self.style.x, self.style.y, &self.layout);
+        self.collider = Collider::from_mesh(
+            bounds,
+            &self.style.transform,
+            &lines.vertices,
+            &lines.indices,
+        );
+        self.style.transform.apply(&bounds, &mut batch.vertices);
+
+        self.vertices = batch.vertices;
+        self.indices = batch.indices;
+    }
+
+    fn get_style(&self) -> Style {
+        self.style
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn get_id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    fn mesh(&mut self) -> Mesh<Vertex> {
+        Mesh {
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl RichText {
+    fn span_font(&self, style: &SpanStyle) -> FontPallet {
+        let font = match (style.font, style.bold, self.bold_font) {
+            (Some(font), _, _) => font,
+            (None, true, Some(bold)) => bold,
+            _ => self.font,
+        };
+        let size = style.size.unwrap_or(self.font_size);
+        if self.sdf {
+            FontPallet::create_sdf_font(font, size)
+        } else {
+            FontPallet::get_font(font, size)
+        }
+    }
+
+    /// Layout options from the style box and paragraph settings
+    pub fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            max_lines: self.max_lines,
+            align: self.align,
+            vertical_align: self.vertical_align,
+            white_space: self.white_space,
+            overflow: self.overflow,
+            line_height: self.line_height,
+            ..LayoutOptions::from_style(&self.style)
+        }
+    }
+
+    /// Add a span with the default style
+    pub fn push(&mut self, text: &str) -> &mut Self {
+        self.span(text, SpanStyle::default())
+    }
+
+    pub fn span(&mut self, text: &str, style: SpanStyle) -> &mut Self {
+        self.spans.push(Span {
+            text: text.to_string(),
+            style,
+        });
+        self
+    }
+
+    /// Add spans from markup, see `parse_markup`
+    pub fn markup(&mut self, markup: &str) -> &mut Self {
+        self.spans.extend(parse_markup(markup));
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.spans.clear();
+        self
+    }
+
+    /// Text of all spans without styles
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Glyph cache was cleared since the last build, see `Text::is_stale`
+    pub fn is_stale(&self) -> bool {
+        self.glyph_generation != glyph_generation()
+    }
+}
+
+impl ElememtResizable for RichText {
+    fn width(&mut self, width: f32) {
+        self.style.width = width;
+    }
+
+    fn height(&mut self, height: f32) {
+        self.style.height = height;
+    }
+
+    fn radius(&mut self, _radius: f32) {}
+}
+
+impl ElementCollider for RichText {
+    fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+        self.collider.contains(point)
+    }
+
+    fn get_collider(&self) -> Aabb2<f32> {
+        self.collider.bounds
+    }
+}
@@ -1,20 +1,41 @@
 use crate::definitions::{Mesh, Vertex};
 use crate::prelude::*;
-use crate::utils::font::{FontBitmap, FontPallet, UvPosition};
-use crate::utils::Batch;
+use crate::utils::font::{cache_runs, glyph_generation, FontId, FontPallet, Glyph, TextMetrics};
+use crate::utils::layout::{
+    layout, measure_layout, LayoutOptions, Overflow, PositionedGlyph, TextAlign, TextLayout,
+    TextRun, VerticalAlign, WhiteSpace,
+};
 use crate::utils::Style;
+use crate::utils::{Batch, Collider};
+use collision::Aabb2;
 
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct Text {
     pub style: Style,
-    pub font: String,
+    pub font: FontId,
+    pub font_size: i32,
+    /// Distance field glyphs, stay sharp when scaled, draw with `Pipeline::draw_sdf`
+    pub sdf: bool,
     pub text: String,
     pub row_height: f32,
+    /// Multiplier of the font line height, 1.0 is the font default
+    pub line_height: f32,
+    pub white_space: WhiteSpace,
+    pub overflow: Overflow,
+    /// Line position in the style box, zero width aligns around `style.x`
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    /// Lines shown at most, for `Overflow::Clip` and `Overflow::Ellipsis`
+    pub max_lines: Option<usize>,
+    /// Glyph positions from the last build
+    pub layout: TextLayout,
     pub last_char_position: cgmath::Vector2<f32>,
 
     pub texture_index: i32,
+    pub glyph_generation: u32,
     pub color: [f32; 4],
+    pub collider: Collider,
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u16>,
     pub scale: f32,
@@ -25,11 +46,22 @@ impl Default for Text {
     fn default() -> Self {
         Self {
             style: Style::default(),
-            font: "".to_string(),
+            font: FontId::default(),
+            font_size: 32,
+            sdf: false,
             last_char_position: cgmath::Vector2::new(0.0, 0.0),
             row_height: 10.0,
+            line_height: 1.0,
+            white_space: WhiteSpace::Normal,
+            overflow: Overflow::Visible,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            max_lines: None,
+            layout: TextLayout::default(),
             color: [1.0, 1.0, 1.0, 1.0],
             texture_index: 0,
+            glyph_generation: 0,
+            collider: Collider::default(),
             vertices: Vec::new(),
             indices: Vec::new(),
             text: "".to_string(),
@@ -43,28 +75,44 @@ impl Default for Text {
 impl ElementCore for Text {
     type Vertex = Vertex;
     fn build(&mut self) {
-        let font = FontPallet::get_font(&self.font);
+        let font = self.font_pallet();
+        let runs = [TextRun {
+            text: &self.text,
+            font,
+        }];
 
-        let mut batch = Batch {
-            vertices: Vec::new(),
-            indices: Vec::new(),
-        };
-
-        self.last_char_position = cgmath::Vector2::new(self.style.x, self.style.y);
-        for key in self.text.clone().chars() {
-            let character = font.get(key);
-            let uv_positions = character.get_uv_position();
+        //Glyphs are cached as one set, so layout does not clear the cache halfway.
+        //A set too big for the glyph pages is laid out anyway and the text stays stale.
+        cache_runs(&runs);
+        self.glyph_generation = glyph_generation();
+        let text_layout = layout(&runs, &self.layout_options());
 
-            //Push letter to new row
-            if (self.last_char_position.x - self.style.x) > self.style.width {
-                self.last_char_position =
-                    cgmath::Vector2::new(self.style.x, self.last_char_position.y + 40.0);
-            }
-
-            let mut letter = self.create_letter(uv_positions, character);
+        let mut batch: Batch<Vertex> = Batch::new();
+        for glyph in text_layout.glyphs.iter().filter(|glyph| glyph.is_visible()) {
+            let mut letter = self.create_letter(glyph);
             batch.add(&mut letter);
         }
 
+        self.last_char_position = match text_layout.glyphs.last() {
+            Some(glyph) => cgmath::Vector2::new(
+                self.style.x + glyph.x + glyph.glyph.advance,
+                self.style.y + text_layout.lines[glyph.line].top,
+            ),
+            None => cgmath::Vector2::new(self.style.x, self.style.y),
+        };
+        self.layout = text_layout;
+
+        //Line boxes, so clicks between glyphs hit the text
+        let bounds = self.style.bounds();
+        let lines = line_boxes(self.style.x, self.style.y, &self.layout);
+        self.collider = Collider::from_mesh(
+            bounds,
+            &self.style.transform,
+            &lines.vertices,
+            &lines.indices,
+        );
+        self.style.transform.apply(&bounds, &mut batch.vertices);
+
         self.vertices = batch.vertices;
         self.indices = batch.indices;
     }
@@ -90,59 +138,50 @@ impl ElementCore for Text {
 }
 
 impl Text {
-    fn create_letter(&mut self, uv: UvPosition, character: &FontBitmap) -> Mesh<Vertex> {
-        let vertices = vec![
-            //Left top corner
-            Vertex {
-                in_position: [
-                    self.last_char_position.x,
-                    self.last_char_position.y + character.offset_y,
-                ],
-                in_color: self.color,
-                tex_pos: [uv.x[0], uv.y[0]],
-                texture_id: self.texture_index,
-            },
-            //Right top corner
-            Vertex {
-                in_position: [
-                    self.last_char_position.x + character.width as f32,
-                    self.last_char_position.y + character.offset_y,
-                ],
-                in_color: self.color,
-                tex_pos: [uv.x[1], uv.y[0]],
-                texture_id: self.texture_index,
-            },
-            //Right bottom corner
-            Vertex {
-                in_position: [
-                    self.last_char_position.x + character.width as f32,
-                    self.last_char_position.y + character.height as f32 + character.offset_y,
-                ],
-                in_color: self.color,
-                tex_pos: [uv.x[1], uv.y[1]],
-                texture_id: self.texture_index,
-            },
-            //Left bottom
-            Vertex {
-                in_position: [
-                    self.last_char_position.x,
-                    self.last_char_position.y + character.height as f32 + character.offset_y,
-                ],
-                in_color: self.color,
-                tex_pos: [uv.x[0], uv.y[1]],
-                texture_id: self.texture_index,
-            },
-        ];
+    fn create_letter(&self, character: &PositionedGlyph) -> Mesh<Vertex> {
+        let (left, top) = character.top_left();
+        glyph_mesh(
+            self.style.x + left,
+            self.style.y + top,
+            &character.glyph,
+            self.color,
+        )
+    }
 
-        let indices = vec![0, 1, 2, 2, 3, 0];
+    /// Layout options from the style box and text settings
+    pub fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            max_lines: self.max_lines,
+            align: self.align,
+            vertical_align: self.vertical_align,
+            white_space: self.white_space,
+            overflow: self.overflow,
+            line_height: self.line_height,
+            ..LayoutOptions::from_style(&self.style)
+        }
+    }
 
-        self.last_char_position = self.last_char_position
-            + cgmath::Vector2 {
-                x: character.width as f32,
-                y: 0.0,
-            };
+    pub fn font_pallet(&self) -> FontPallet {
+        if self.sdf {
+            FontPallet::create_sdf_font(self.font, self.font_size)
+        } else {
+            FontPallet::get_font(self.font, self.font_size)
+        }
+    }
 
-        Mesh { vertices, indices }
+    /// Size of the text with the current style, without building it or rasterizing glyphs
+    pub fn measure(&self) -> TextMetrics {
+        let runs = [TextRun {
+            text: &self.text,
+            font: self.font_pallet(),
+        }];
+        TextMetrics::from_layout(&measure_layout(&runs, &self.layout_options()))
+    }
+
+    /// Glyph cache was cleared since the last build, build the text again.
+    /// Right after a build, the text glyphs did not fit in the glyph pages.
+    pub fn is_stale(&self) -> bool {
+        self.glyph_generation != glyph_generation()
     }
 
     pub fn text(&mut self, text: &str) -> &mut Self {
@@ -151,6 +190,88 @@ impl Text {
     }
 }
 
+/// Quad of a glyph with the top left corner at x, y
+pub(crate) fn glyph_mesh(x: f32, y: f32, glyph: &Glyph, color: [f32; 4]) -> Mesh<Vertex> {
+    let uv = glyph.uv;
+    let vertices = vec![
+        //Left top corner
+        Vertex {
+            in_position: [x, y],
+            in_color: color,
+            tex_pos: [uv.x[0], uv.y[0]],
+            texture_id: glyph.page,
+        },
+        //Right top corner
+        Vertex {
+            in_position: [x + glyph.width, y],
+            in_color: color,
+            tex_pos: [uv.x[1], uv.y[0]],
+            texture_id: glyph.page,
+        },
+        //Right bottom corner
+        Vertex {
+            in_position: [x + glyph.width, y + glyph.height],
+            in_color: color,
+            tex_pos: [uv.x[1], uv.y[1]],
+            texture_id: glyph.page,
+        },
+        //Left bottom
+        Vertex {
+            in_position: [x, y + glyph.height],
+            in_color: color,
+            tex_pos: [uv.x[0], uv.y[1]],
+            texture_id: glyph.page,
+        },
+    ];
+
+    let indices = vec![0, 1, 2, 2, 3, 0];
+
+    Mesh { vertices, indices }
+}
+
+/// Untextured quad, texture id -1 makes the text shader use the plain color
+pub(crate) fn solid_mesh(x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) -> Mesh<Vertex> {
+    let corners = [
+        [x, y],
+        [x + width, y],
+        [x + width, y + height],
+        [x, y + height],
+    ];
+    let vertices = corners
+        .iter()
+        .map(|corner| Vertex {
+            in_position: *corner,
+            in_color: color,
+            tex_pos: [0.0, 0.0],
+            texture_id: -1,
+        })
+        .collect();
+
+    Mesh {
+        vertices,
+        indices: vec![0, 1, 2, 2, 3, 0],
+    }
+}
+
+/// Untransformed line boxes of a layout placed at x, y, the hit area of text
+pub(crate) fn line_boxes(x: f32, y: f32, layout: &TextLayout) -> Mesh<Vertex> {
+    let mut batch: Batch<Vertex> = Batch::new();
+    for line in layout.lines.iter() {
+        batch.add(&mut solid_mesh(
+            x + line.x,
+            y + line.top,
+            line.width,
+            line.height,
+            [0.0; 4],
+        ));
+    }
+
+    Mesh {
+        vertices: batch.vertices,
+        indices: batch.indices,
+    }
+}
+
 impl ElememtResizable for Text {
     fn width(&mut self, width: f32) {
         self.style.width = width;
@@ -162,3 +283,86 @@ impl ElememtResizable for Text {
 
     fn radius(&mut self, _radius: f32) {}
 }
+
+impl ElementCollider for Text {
+    fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+        self.collider.contains(point)
+    }
+
+    fn get_collider(&self) -> Aabb2<f32> {
+        self.collider.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::font::{load_font_bytes, measure};
+
+    fn font() -> FontId {
+        load_font_bytes(include_bytes!("../../examples/text/assets/Roboto.ttf"))
+    }
+
+    #[test]
+    fn measure_matches_the_built_text() {
+        let font = font();
+        let mut line_counts = Vec::new();
+        for width in [0.0, 160.0, 60.0].iter() {
+            let mut text = Text {
+                font,
+                font_size: 18,
+                style: Style {
+                    x: 10.0,
+                    y: 20.0,
+                    width: *width,
+                    ..Style::default()
+                },
+                ..Text::default()
+            };
+            text.text("Measure this text\nand wrap it at the box width");
+            let metrics = text.measure();
+            let max_width = if *width > 0.0 { Some(*width) } else { None };
+            assert_eq!(metrics, measure(font, 18, &text.text, max_width));
+
+            text.build();
+            assert_eq!(metrics, TextMetrics::from_layout(&text.layout));
+
+            //Glyph ink can reach a bit past the advance of the last glyph
+            let mesh = text.mesh().bounds().unwrap();
+            assert!(mesh.min.x >= 10.0 - 2.0 && mesh.max.x <= 10.0 + metrics.width + 2.0);
+            assert!(mesh.min.y >= 20.0 && mesh.max.y <= 20.0 + metrics.height);
+            line_counts.push(metrics.line_count);
+        }
+
+        assert_eq!(line_counts[0], 2);
+        assert!(line_counts[1] > line_counts[0] && line_counts[2] > line_counts[1]);
+    }
+
+    #[test]
+    fn gaps_between_glyphs_hit_the_text() {
+        let mut text = Text {
+            font: font(),
+            style: Style {
+                x: 10.0,
+                y: 20.0,
+                ..Style::default()
+            },
+            ..Text::default()
+        };
+        text.text("a . b").build();
+
+        let line = text.layout.lines[0];
+        let space = &text.layout.glyphs[1];
+        let middle = 20.0 + line.top + line.height / 2.0;
+        //Space between words and the area above a short glyph
+        assert!(text.contains(cgmath::Point2::new(10.0 + space.x + 1.0, middle)));
+        assert!(text.contains(cgmath::Point2::new(
+            10.0 + line.x + 1.0,
+            20.0 + line.top + 1.0
+        )));
+        assert!(!text.contains(cgmath::Point2::new(
+            10.0 + line.x + line.width + 5.0,
+            middle
+        )));
+    }
+}
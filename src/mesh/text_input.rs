@@ -0,0 +1,449 @@
+use crate::definitions::{Mesh, Vertex};
+use crate::mesh::text::{solid_mesh, Text};
+use crate::prelude::*;
+use crate::utils::events::TextChar;
+use crate::utils::layout::{VerticalAlign, WhiteSpace};
+use crate::utils::{Batch, Collider, Event, Style, Transform};
+use collision::Aabb2;
+use std::cmp::Ordering;
+
+/// Reported by `TextInput::update`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextInputEvent {
+    Changed(String),
+    /// Enter was pressed
+    Submitted(String),
+}
+
+/// Single line text field, rendered with `Text` and drawn with the text pipeline.
+/// Call `update` with every event and `blink` every frame.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct TextInput {
+    pub style: Style,
+    pub value: String,
+    /// Shown in `placeholder_color`, while the value is empty
+    pub placeholder: String,
+    pub placeholder_color: [f32; 4],
+    /// Characters are drawn as `mask`
+    pub password: bool,
+    pub mask: char,
+    pub focused: bool,
+    /// Caret and selection start, in characters
+    pub caret: usize,
+    pub anchor: usize,
+    pub caret_color: [f32; 4],
+    pub selection_color: [f32; 4],
+    /// Seconds between caret blinks
+    pub blink_interval: f32,
+    /// Font, size and color of the value, text and style are set on build
+    pub text: Text,
+
+    blink_time: f32,
+    caret_visible: bool,
+    scroll: f32,
+    mouse_down: bool,
+    pub collider: Collider,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+    pub id: Option<String>,
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self {
+            style: Style::default(),
+            value: String::new(),
+            placeholder: String::new(),
+            placeholder_color: [0.5, 0.5, 0.5, 1.0],
+            password: false,
+            mask: '•',
+            focused: false,
+            caret: 0,
+            anchor: 0,
+            caret_color: [1.0, 1.0, 1.0, 1.0],
+            selection_color: [0.2, 0.4, 0.8, 0.6],
+            blink_interval: 0.5,
+            text: Text {
+                white_space: WhiteSpace::Pre,
+                vertical_align: VerticalAlign::Middle,
+                ..Text::default()
+            },
+            blink_time: 0.0,
+            caret_visible: true,
+            scroll: 0.0,
+            mouse_down: false,
+            collider: Collider::default(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            id: None,
+        }
+    }
+}
+
+//Cut an untransformed glyph quad to the x range, texture coordinates are cut with it.
+//None, when the quad is completely outside.
+fn clip_quad(quad: &[Vertex], min_x: f32, max_x: f32) -> Option<Vec<Vertex>> {
+    let left = quad.iter().min_by(|a, b| {
+        a.in_position[0]
+            .partial_cmp(&b.in_position[0])
+            .unwrap_or(Ordering::Equal)
+    })?;
+    let right = quad.iter().max_by(|a, b| {
+        a.in_position[0]
+            .partial_cmp(&b.in_position[0])
+            .unwrap_or(Ordering::Equal)
+    })?;
+    let (left_x, right_x) = (left.in_position[0], right.in_position[0]);
+    if right_x <= min_x || left_x >= max_x {
+        return None;
+    }
+
+    let (left_u, right_u) = (left.tex_pos[0], right.tex_pos[0]);
+    let width = right_x - left_x;
+    Some(
+        quad.iter()
+            .map(|vertex| {
+                let x = vertex.in_position[0].max(min_x).min(max_x);
+                let u = if width > 0.0 {
+                    left_u + (right_u - left_u) * (x - left_x) / width
+                } else {
+                    vertex.tex_pos[0]
+                };
+                Vertex {
+                    in_position: [x, vertex.in_position[1]],
+                    tex_pos: [u, vertex.tex_pos[1]],
+                    ..*vertex
+                }
+            })
+            .collect(),
+    )
+}
+
+#[allow(dead_code)]
+impl ElementCore for TextInput {
+    type Vertex = Vertex;
+    fn build(&mut self) {
+        let placeholder = self.value.is_empty() && !self.placeholder.is_empty();
+        let color = self.text.color;
+        self.text.text = if placeholder {
+            self.placeholder.clone()
+        } else if self.password {
+            self.value.chars().map(|_| self.mask).collect()
+        } else {
+            self.value.clone()
+        };
+        if placeholder {
+            self.text.color = self.placeholder_color;
+        }
+
+        //Transform is applied once to the whole field
+        self.text.style = Style {
+            width: 0.0,
+            transform: Transform::default(),
+            ..self.style
+        };
+        self.text.build();
+        self.text.color = color;
+
+        //Layout is relative to the text position, so scrolling only moves the glyphs
+        self.scroll_to_caret(placeholder);
+        for vertex in self.text.vertices.iter_mut() {
+            vertex.in_position[0] -= self.scroll;
+        }
+
+        let (top, height) = self
+            .text
+            .layout
+            .lines
+            .first()
+            .map_or((0.0, 0.0), |line| (line.top, line.height));
+        let y = self.style.y + top;
+        let mut batch: Batch<Vertex> = Batch::new();
+
+        let (start, end) = self.selection();
+        if self.focused && start != end {
+            let left = self.caret_x(start);
+            let right = self.caret_x(end);
+            batch.add(&mut solid_mesh(
+                self.screen_x(left),
+                y,
+                right - left,
+                height,
+                self.selection_color,
+            ));
+        }
+
+        //Glyphs are cut at the field edges, text scrolls with the caret
+        let (min_x, max_x) = (self.style.x, self.style.x + self.style.width);
+        for quad in self.text.vertices.chunks(4) {
+            let vertices = if self.style.width > 0.0 {
+                clip_quad(quad, min_x, max_x)
+            } else {
+                Some(quad.to_vec())
+            };
+            if let Some(vertices) = vertices {
+                batch.add(&mut Mesh {
+                    vertices,
+                    indices: vec![0, 1, 2, 2, 3, 0],
+                });
+            }
+        }
+
+        if self.focused && self.caret_visible {
+            let x = if placeholder {
+                0.0
+            } else {
+                self.caret_x(self.caret)
+            };
+            batch.add(&mut solid_mesh(
+                self.screen_x(x),
+                y,
+                (self.text.font_size as f32 / 16.0).max(1.0),
+                height,
+                self.caret_color,
+            ));
+        }
+
+        let bounds = self.style.bounds();
+        self.collider = Collider::new(bounds, &self.style.transform);
+        self.style.transform.apply(&bounds, &mut batch.vertices);
+
+        self.vertices = batch.vertices;
+        self.indices = batch.indices;
+    }
+
+    fn get_style(&self) -> Style {
+        self.style
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn get_id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    fn mesh(&mut self) -> Mesh<Vertex> {
+        Mesh {
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+impl TextInput {
+    fn char_count(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    //Byte offset of a character index in the value
+    fn byte(&self, index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(index)
+            .map_or(self.value.len(), |(byte, _)| byte)
+    }
+
+    //Caret position from the text start, display text has one glyph per character
+    fn caret_x(&self, index: usize) -> f32 {
+        let glyphs = &self.text.layout.glyphs;
+        match glyphs.get(index) {
+            Some(glyph) => glyph.x,
+            None => glyphs
+                .last()
+                .map_or(0.0, |glyph| glyph.x + glyph.glyph.advance),
+        }
+    }
+
+    fn screen_x(&self, x: f32) -> f32 {
+        self.style.x - self.scroll + x
+    }
+
+    //Keep the caret inside the field width
+    fn scroll_to_caret(&mut self, placeholder: bool) {
+        if self.style.width <= 0.0 || placeholder {
+            self.scroll = 0.0;
+            return;
+        }
+        let caret = self.caret_x(self.caret);
+        let end = self.caret_x(self.char_count());
+        if caret - self.scroll > self.style.width {
+            self.scroll = caret - self.style.width;
+        }
+        if caret < self.scroll {
+            self.scroll = caret;
+        }
+        self.scroll = self.scroll.min(end - self.style.width).max(0.0);
+    }
+
+    /// Character under a screen x position, rounded to the nearest caret position
+    pub fn index_at(&self, x: f32) -> usize {
+        let x = x - self.style.x + self.scroll;
+        (0..=self.char_count())
+            .min_by(|a, b| {
+                let a = (self.caret_x(*a) - x).abs();
+                let b = (self.caret_x(*b) - x).abs();
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Selected character range, empty when nothing is selected
+    pub fn selection(&self) -> (usize, usize) {
+        (self.caret.min(self.anchor), self.caret.max(self.anchor))
+    }
+
+    pub fn selected_text(&self) -> &str {
+        let (start, end) = self.selection();
+        &self.value[self.byte(start)..self.byte(end)]
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.char_count();
+    }
+
+    /// Replace the value, caret goes to the end
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.caret = self.char_count();
+        self.anchor = self.caret;
+    }
+
+    //Remove the selection, returns true when something was selected
+    fn delete_selection(&mut self) -> bool {
+        let (start, end) = self.selection();
+        if start == end {
+            return false;
+        }
+        let range = self.byte(start)..self.byte(end);
+        self.value.replace_range(range, "");
+        self.caret = start;
+        self.anchor = start;
+        true
+    }
+
+    fn move_caret(&mut self, index: usize, select: bool) {
+        self.caret = index.min(self.char_count());
+        if !select {
+            self.anchor = self.caret;
+        }
+    }
+
+    /// Handle mouse and keyboard input, the field is rebuilt when it changed
+    pub fn update(&mut self, events: &Event) -> Vec<TextInputEvent> {
+        let mut reported = Vec::new();
+        let (caret, anchor, focused) = (self.caret, self.anchor, self.focused);
+        let previous = self.value.clone();
+        let shift = events.keyboard.shift();
+
+        let pressed = events.mouse.on_left_click();
+        if pressed && !self.mouse_down {
+            self.focused = self.contains(events.mouse.position);
+            if self.focused {
+                let index = self.index_at(events.mouse.x());
+                self.move_caret(index, shift);
+            }
+        } else if pressed && self.focused {
+            //Dragging selects
+            let index = self.index_at(events.mouse.x());
+            self.move_caret(index, true);
+        }
+        self.mouse_down = pressed;
+
+        if self.focused {
+            for input in events.text.iter() {
+                let (start, end) = self.selection();
+                match input {
+                    TextChar::Char(ch) => {
+                        self.delete_selection();
+                        let byte = self.byte(self.caret);
+                        self.value.insert(byte, *ch);
+                        self.move_caret(self.caret + 1, false);
+                    }
+                    TextChar::Back => {
+                        if !self.delete_selection() && self.caret > 0 {
+                            let byte = self.byte(self.caret - 1);
+                            self.value.remove(byte);
+                            self.move_caret(self.caret - 1, false);
+                        }
+                    }
+                    TextChar::Delete => {
+                        if !self.delete_selection() && self.caret < self.char_count() {
+                            let byte = self.byte(self.caret);
+                            self.value.remove(byte);
+                        }
+                    }
+                    TextChar::Left => {
+                        let index = if start != end && !shift {
+                            start
+                        } else {
+                            self.caret.saturating_sub(1)
+                        };
+                        self.move_caret(index, shift);
+                    }
+                    TextChar::Right => {
+                        let index = if start != end && !shift {
+                            end
+                        } else {
+                            self.caret + 1
+                        };
+                        self.move_caret(index, shift);
+                    }
+                    TextChar::Home => self.move_caret(0, shift),
+                    TextChar::End => self.move_caret(self.char_count(), shift),
+                    TextChar::Enter => reported.push(TextInputEvent::Submitted(self.value.clone())),
+                }
+            }
+        }
+
+        let changed = self.value != previous;
+        if changed {
+            reported.insert(0, TextInputEvent::Changed(self.value.clone()));
+        }
+        if changed || caret != self.caret || anchor != self.anchor || focused != self.focused {
+            //Caret stays visible while typing
+            self.blink_time = 0.0;
+            self.caret_visible = true;
+            self.build();
+        }
+        reported
+    }
+
+    /// Advance the caret blink, rebuilds when the caret appears or disappears
+    pub fn blink(&mut self, delta_time: f32) {
+        if !self.focused || self.blink_interval <= 0.0 {
+            return;
+        }
+        self.blink_time += delta_time;
+        if self.blink_time >= self.blink_interval {
+            self.blink_time %= self.blink_interval;
+            self.caret_visible = !self.caret_visible;
+            self.build();
+        }
+    }
+}
+
+impl ElememtResizable for TextInput {
+    fn width(&mut self, width: f32) {
+        self.style.width = width;
+    }
+
+    fn height(&mut self, height: f32) {
+        self.style.height = height;
+    }
+
+    fn radius(&mut self, _radius: f32) {}
+}
+
+impl ElementCollider for TextInput {
+    fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+        self.collider.contains(point)
+    }
+
+    fn get_collider(&self) -> Aabb2<f32> {
+        self.collider.bounds
+    }
+}
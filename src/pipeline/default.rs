@@ -1,7 +1,10 @@
-use crate::definitions::Vertex;
+use crate::definitions::{Texture, Vertex};
+use crate::utils::atlas::Atlas;
+use crate::utils::texture::TextureOptions;
 use crate::utils::{load_glsl, ShaderStage};
 use crate::System;
 use cgmath::{self, prelude::*};
+use image::RgbaImage;
 
 #[allow(dead_code)]
 pub struct Pipeline {
@@ -226,110 +229,126 @@ impl Pipeline {
         &mut self,
         system: &mut System,
         paths: Vec<&str>,
+    ) -> wgpu::BindGroup {
+        self.create_textures_array_with(system, paths, TextureOptions::default())
+    }
+
+    pub fn create_textures_array_with(
+        &mut self,
+        system: &mut System,
+        paths: Vec<&str>,
+        options: TextureOptions,
     ) -> wgpu::BindGroup {
         let absolute_path = std::env::current_dir().expect("Bad image path");
 
-        let (mut img_width, mut img_height) = (0, 0);
+        let images = paths
+            .iter()
+            .map(|src| image::open(absolute_path.join(src)).unwrap())
+            .collect::<Vec<_>>();
 
-        let faces = paths
+        self.create_textures_array_from_images(system, images, options)
+    }
+
+    /// Texture array from encoded images, like `include_bytes!("image.png")`
+    pub fn create_textures_array_from_bytes(
+        &mut self,
+        system: &mut System,
+        files: &[&[u8]],
+        options: TextureOptions,
+    ) -> wgpu::BindGroup {
+        let images = files
             .iter()
-            .map(|src| {
-                let img = image::open(absolute_path.join(src)).unwrap().to_rgba();
+            .map(|bytes| image::load_from_memory(bytes).expect("Failed to decode image"))
+            .collect::<Vec<_>>();
+
+        self.create_textures_array_from_images(system, images, options)
+    }
+
+    pub fn create_textures_array_from_images(
+        &mut self,
+        system: &mut System,
+        images: Vec<image::DynamicImage>,
+        options: TextureOptions,
+    ) -> wgpu::BindGroup {
+        let (mut img_width, mut img_height) = (0, 0);
+
+        let faces = images
+            .into_iter()
+            .map(|img| {
+                let img = img.to_rgba();
                 let (width, height) = img.dimensions();
+                assert!(
+                    img_width == 0 || (img_width == width && img_height == height),
+                    "Texture array images must have the same size, use AtlasBuilder for mixed sizes"
+                );
                 img_width = width;
                 img_height = height;
                 img.into_raw()
             })
             .collect::<Vec<_>>();
 
-        let texture_extent = wgpu::Extent3d {
-            width: img_width,
-            height: img_height,
-            depth: 1,
-        };
-
-        let texture = system.device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_extent,
-            array_layer_count: faces.len() as u32,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            usage: wgpu::TextureUsage::COPY_DST
-                | wgpu::TextureUsage::SAMPLED
-                | wgpu::TextureUsage::WRITE_ALL,
-        });
-
-        let sampler = system.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            lod_min_clamp: -100.0,
-            lod_max_clamp: 100.0,
-            compare_function: wgpu::CompareFunction::Always,
-        });
-
-        let mut encoder = system
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        self.create_textures_array_raw(system, img_width, img_height, faces, options)
+    }
 
-        for (i, image) in faces.iter().enumerate() {
-            let image_buffer = system
-                .device
-                .create_buffer_mapped(image.len(), wgpu::BufferUsage::COPY_SRC)
-                .fill_from_slice(&image);
-
-            encoder.copy_buffer_to_texture(
-                wgpu::BufferCopyView {
-                    buffer: &image_buffer,
-                    offset: 0,
-                    row_pitch: 4 * img_width,
-                    image_height: img_height,
-                },
-                wgpu::TextureCopyView {
-                    texture: &texture,
-                    mip_level: 0,
-                    array_layer: i as u32,
-                    origin: wgpu::Origin3d {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 0.0,
-                    },
-                },
-                texture_extent,
-            );
-        }
+    /// Texture array from packed atlas pages
+    pub fn create_atlas(
+        &mut self,
+        system: &mut System,
+        atlas: &Atlas,
+        options: TextureOptions,
+    ) -> wgpu::BindGroup {
+        let faces = atlas
+            .pages
+            .iter()
+            .map(|page| page.clone().into_raw())
+            .collect::<Vec<_>>();
 
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            dimension: wgpu::TextureViewDimension::D2Array,
-            aspect: wgpu::TextureAspect::default(),
-            base_mip_level: 0,
-            level_count: 1,
-            base_array_layer: 0,
-            array_layer_count: faces.len() as u32,
-        });
+        self.create_textures_array_raw(system, atlas.width, atlas.height, faces, options)
+    }
 
-        let bind_group = system.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.texture_layout,
-            bindings: &[
-                wgpu::Binding {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::Binding {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
+    /// Texture array from RGBA pixels, every layer has the same size
+    pub fn create_textures_array_raw(
+        &mut self,
+        system: &mut System,
+        img_width: u32,
+        img_height: u32,
+        faces: Vec<Vec<u8>>,
+        options: TextureOptions,
+    ) -> wgpu::BindGroup {
+        self.create_texture(system, img_width, img_height, faces, options)
+            .bind_group
+    }
 
-        system.queue.submit(&[encoder.finish()]);
+    /// Editable texture array from RGBA pixels, every layer has the same size
+    pub fn create_texture(
+        &mut self,
+        system: &mut System,
+        img_width: u32,
+        img_height: u32,
+        faces: Vec<Vec<u8>>,
+        options: TextureOptions,
+    ) -> Texture {
+        let layers = faces
+            .into_iter()
+            .map(|face| {
+                RgbaImage::from_raw(img_width, img_height, face)
+                    .expect("Raw texture must be RGBA with 4 bytes per pixel")
+            })
+            .collect();
+
+        Texture::new(
+            system,
+            &self.texture_layout,
+            img_width,
+            img_height,
+            layers,
+            options,
+        )
+    }
 
-        bind_group
+    /// Layout for `Texture::add_layer`
+    pub fn texture_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_layout
     }
 
     pub fn draw(
@@ -381,3 +400,75 @@ impl Pipeline {
         system.queue.submit(&[encoder.finish()]);
     }
 }
+
+//Rows of buffer to texture copies are aligned to 256 bytes
+const ROW_ALIGNMENT: u32 = 256;
+
+/// Copy RGBA pixels to a rectangle `(x, y, width, height)` of one texture layer and mip level
+pub fn copy_to_layer(
+    system: &System,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    layer: u32,
+    mip_level: u32,
+    rect: (u32, u32, u32, u32),
+    data: &[u8],
+) {
+    copy_pixels_to_layer(system, encoder, texture, layer, mip_level, rect, data, 4);
+}
+
+/// Copy pixels of any format with `pixel_size` bytes per pixel, like 1 for R8 textures.
+/// Empty regions copy nothing.
+#[allow(clippy::too_many_arguments)]
+pub fn copy_pixels_to_layer(
+    system: &System,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    layer: u32,
+    mip_level: u32,
+    rect: (u32, u32, u32, u32),
+    data: &[u8],
+    pixel_size: u32,
+) {
+    let (x, y, width, height) = rect;
+    let row = (pixel_size * width) as usize;
+    //Nothing to copy, an empty row would also break the row chunks
+    if row == 0 || height == 0 {
+        return;
+    }
+    let row_pitch = (pixel_size * width + ROW_ALIGNMENT - 1) / ROW_ALIGNMENT * ROW_ALIGNMENT;
+
+    let mut padded = vec![0u8; row_pitch as usize * height as usize];
+    for (source, target) in data.chunks(row).zip(padded.chunks_mut(row_pitch as usize)) {
+        target[..row].copy_from_slice(source);
+    }
+
+    let buffer = system
+        .device
+        .create_buffer_mapped(padded.len(), wgpu::BufferUsage::COPY_SRC)
+        .fill_from_slice(&padded);
+
+    encoder.copy_buffer_to_texture(
+        wgpu::BufferCopyView {
+            buffer: &buffer,
+            offset: 0,
+            row_pitch,
+            image_height: height,
+        },
+        wgpu::TextureCopyView {
+            texture,
+            mip_level,
+            array_layer: layer,
+            origin: wgpu::Origin3d {
+                x: x as f32,
+                y: y as f32,
+                z: 0.0,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+}
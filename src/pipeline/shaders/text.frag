@@ -10,7 +10,14 @@ layout(set = 1, binding = 1) uniform sampler s_color;
 layout(location = 0) out vec4 o_target;
 
 void main() {
-   o_target = vec4(in_color.rgb, texture(sampler2DArray(in_texture, s_color), vec3(uv_cords, layer_index)));
+   //Negative layer is an untextured quad, like underline or background
+   if (layer_index < 0) {
+      o_target = in_color;
+      return;
+   }
+
+   float coverage = texture(sampler2DArray(in_texture, s_color), vec3(uv_cords, layer_index)).r;
+   o_target = vec4(in_color.rgb, in_color.a * coverage);
 
    // o_target = vec4(in_color.rgb, 1.0);
 }
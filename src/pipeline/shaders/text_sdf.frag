@@ -0,0 +1,45 @@
+#version 450
+
+layout(location = 0) in vec2 uv_cords;
+layout(location = 1) in vec4 in_color;
+layout(location = 2) flat in int layer_index;
+
+layout(set = 0, binding = 2) uniform Effects {
+    vec4 outline_color;
+    vec4 glow_color;
+    float outline_width;
+    float glow_width;
+};
+
+layout(set = 1, binding = 0) uniform texture2DArray in_texture;
+layout(set = 1, binding = 1) uniform sampler s_color;
+
+layout(location = 0) out vec4 o_target;
+
+void main() {
+   //Negative layer is an untextured quad, like underline or background
+   if (layer_index < 0) {
+      o_target = in_color;
+      return;
+   }
+
+   //Outline is at 0.5, edges are smoothed over about one screen pixel at every scale
+   float distance = texture(sampler2DArray(in_texture, s_color), vec3(uv_cords, layer_index)).r;
+   float smoothing = max(fwidth(distance) * 0.5, 0.001);
+   float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, distance);
+
+   vec4 color = vec4(in_color.rgb, in_color.a * fill);
+   if (outline_width > 0.0) {
+      float edge = 0.5 - outline_width;
+      float outline = smoothstep(edge - smoothing, edge + smoothing, distance);
+      color = vec4(mix(outline_color.rgb, in_color.rgb, fill), mix(outline_color.a, in_color.a, fill) * outline);
+   }
+
+   //Glow fades out below the text
+   if (glow_width > 0.0) {
+      float glow = smoothstep(0.5 - glow_width, 0.5, distance) * glow_color.a;
+      color = vec4(mix(glow_color.rgb, color.rgb, color.a), color.a + glow * (1.0 - color.a));
+   }
+
+   o_target = color;
+}
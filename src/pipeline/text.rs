@@ -1,14 +1,138 @@
 use crate::definitions::Vertex;
-use crate::utils::{load_glsl, FontPallet, ShaderStage};
+use crate::pipeline::default::copy_pixels_to_layer;
+use crate::utils::font::{glyph_page, take_glyph_updates};
+use crate::utils::{load_glsl, ShaderStage};
 use crate::System;
 use cgmath::{self, prelude::*};
 
 #[allow(dead_code)]
 pub struct Pipeline {
     uniform_buffer: wgpu::Buffer,
+    effects_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     texture_layout: wgpu::BindGroupLayout,
     render_pipeline: wgpu::RenderPipeline,
+    sdf_pipeline: wgpu::RenderPipeline,
+    glyphs: Option<GlyphTexture>,
+}
+
+/// Outline and glow of distance field text. Widths are in distance field units,
+/// 0.5 reaches `SDF_SPREAD` pixels of `SDF_SIZE` from the outline.
+#[derive(Copy, Clone, Debug)]
+pub struct SdfEffects {
+    pub outline_color: [f32; 4],
+    pub outline_width: f32,
+    pub glow_color: [f32; 4],
+    pub glow_width: f32,
+}
+
+impl Default for SdfEffects {
+    fn default() -> Self {
+        Self {
+            outline_color: [0.0, 0.0, 0.0, 1.0],
+            outline_width: 0.0,
+            glow_color: [0.0, 0.0, 0.0, 0.0],
+            glow_width: 0.0,
+        }
+    }
+}
+
+impl SdfEffects {
+    //Layout of the Effects uniform block in text_sdf.frag
+    fn to_uniform(&self) -> [f32; 12] {
+        let (outline, glow) = (self.outline_color, self.glow_color);
+        [
+            outline[0],
+            outline[1],
+            outline[2],
+            outline[3],
+            glow[0],
+            glow[1],
+            glow[2],
+            glow[3],
+            self.outline_width,
+            self.glow_width,
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+/// Glyph atlas pages on the GPU, one layer per page
+struct GlyphTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    page_size: u32,
+    pages: u32,
+    generation: u32,
+}
+
+impl GlyphTexture {
+    fn new(
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        page_size: u32,
+        pages: u32,
+        generation: u32,
+    ) -> Self {
+        let sampler = system.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare_function: wgpu::CompareFunction::Always,
+        });
+
+        let texture = system.device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: page_size,
+                height: page_size,
+                depth: 1,
+            },
+            array_layer_count: pages,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsage::COPY_DST | wgpu::TextureUsage::SAMPLED,
+        });
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            format: wgpu::TextureFormat::R8Unorm,
+            dimension: wgpu::TextureViewDimension::D2Array,
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            array_layer_count: pages,
+        });
+
+        let bind_group = system.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            bind_group,
+            page_size,
+            pages,
+            generation,
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -29,6 +153,11 @@ impl Pipeline {
                             visibility: wgpu::ShaderStage::VERTEX,
                             ty: wgpu::BindingType::UniformBuffer { dynamic: false },
                         },
+                        wgpu::BindGroupLayoutBinding {
+                            binding: 2,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                        },
                     ],
                 });
 
@@ -42,7 +171,7 @@ impl Pipeline {
                             visibility: wgpu::ShaderStage::FRAGMENT,
                             ty: wgpu::BindingType::SampledTexture {
                                 multisampled: false,
-                                dimension: wgpu::TextureViewDimension::D2,
+                                dimension: wgpu::TextureViewDimension::D2Array,
                             },
                         },
                         wgpu::BindGroupLayoutBinding {
@@ -77,6 +206,11 @@ impl Pipeline {
             .create_buffer_mapped(16, wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST)
             .fill_from_slice(transform_buf);
 
+        let effects_buffer = system
+            .device
+            .create_buffer_mapped(12, wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST)
+            .fill_from_slice(&SdfEffects::default().to_uniform());
+
         let bind_group = system.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
             bindings: &[
@@ -94,181 +228,220 @@ impl Pipeline {
                         range: 0..64,
                     },
                 },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &effects_buffer,
+                        range: 0..48,
+                    },
+                },
             ],
         });
 
         let vs_bytes = load_glsl(include_str!("shaders/text.vert"), ShaderStage::Vertex);
         let fs_bytes = load_glsl(include_str!("shaders/text.frag"), ShaderStage::Fragment);
+        let sdf_bytes = load_glsl(include_str!("shaders/text_sdf.frag"), ShaderStage::Fragment);
         let vs_module = system.device.create_shader_module(&vs_bytes);
         let fs_module = system.device.create_shader_module(&fs_bytes);
+        let sdf_module = system.device.create_shader_module(&sdf_bytes);
 
         let render_pipeline =
-            system
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    layout: &pipeline_layout,
-                    vertex_stage: wgpu::ProgrammableStageDescriptor {
-                        module: &vs_module,
-                        entry_point: "main",
-                    },
-                    fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                        module: &fs_module,
-                        entry_point: "main",
-                    }),
-                    rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: wgpu::CullMode::None,
-                        depth_bias: 0,
-                        depth_bias_slope_scale: 0.0,
-                        depth_bias_clamp: 0.0,
-                    }),
-                    primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                    color_states: &[wgpu::ColorStateDescriptor {
-                        format: wgpu::TextureFormat::Bgra8Unorm,
-                        color_blend: wgpu::BlendDescriptor {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha_blend: wgpu::BlendDescriptor {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        write_mask: wgpu::ColorWrite::ALL,
-                    }],
-                    depth_stencil_state: None,
-                    index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[wgpu::VertexBufferDescriptor {
-                        stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                        step_mode: wgpu::InputStepMode::Vertex,
-                        attributes: &[
-                            wgpu::VertexAttributeDescriptor {
-                                format: wgpu::VertexFormat::Float2,
-                                offset: 0,
-                                shader_location: 0,
-                            },
-                            wgpu::VertexAttributeDescriptor {
-                                format: wgpu::VertexFormat::Float4,
-                                offset: 8,
-                                shader_location: 1,
-                            },
-                            wgpu::VertexAttributeDescriptor {
-                                format: wgpu::VertexFormat::Float2,
-                                offset: 24,
-                                shader_location: 2,
-                            },
-                            wgpu::VertexAttributeDescriptor {
-                                format: wgpu::VertexFormat::Int,
-                                offset: 32,
-                                shader_location: 3,
-                            },
-                        ],
-                    }],
-                    sample_count: 1,
-                    sample_mask: !0,
-                    alpha_to_coverage_enabled: false,
-                });
+            Pipeline::create_render_pipeline(system, &pipeline_layout, &vs_module, &fs_module);
+        let sdf_pipeline =
+            Pipeline::create_render_pipeline(system, &pipeline_layout, &vs_module, &sdf_module);
 
         Self {
             uniform_buffer,
+            effects_buffer,
             bind_group,
             texture_layout,
             render_pipeline,
+            sdf_pipeline,
+            glyphs: None,
         }
     }
 
-    pub fn create_font_texture(
-        &mut self,
-        system: &mut System,
-        font_instance: &FontPallet,
-    ) -> wgpu::BindGroup {
-        let sampler = system.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 0.0,
-            compare_function: wgpu::CompareFunction::Always,
-        });
+    fn create_render_pipeline(
+        system: &System,
+        layout: &wgpu::PipelineLayout,
+        vs_module: &wgpu::ShaderModule,
+        fs_module: &wgpu::ShaderModule,
+    ) -> wgpu::RenderPipeline {
+        system
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                layout,
+                vertex_stage: wgpu::ProgrammableStageDescriptor {
+                    module: vs_module,
+                    entry_point: "main",
+                },
+                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                    module: fs_module,
+                    entry_point: "main",
+                }),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: wgpu::CullMode::None,
+                    depth_bias: 0,
+                    depth_bias_slope_scale: 0.0,
+                    depth_bias_clamp: 0.0,
+                }),
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                color_states: &[wgpu::ColorStateDescriptor {
+                    format: wgpu::TextureFormat::Bgra8Unorm,
+                    color_blend: wgpu::BlendDescriptor {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha_blend: wgpu::BlendDescriptor {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+                depth_stencil_state: None,
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float2,
+                            offset: 0,
+                            shader_location: 0,
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: 8,
+                            shader_location: 1,
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float2,
+                            offset: 24,
+                            shader_location: 2,
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Int,
+                            offset: 32,
+                            shader_location: 3,
+                        },
+                    ],
+                }],
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+            })
+    }
 
-        let texture_extent = wgpu::Extent3d {
-            width: font_instance.max_w as u32,
-            height: font_instance.max_h as u32,
-            depth: 1,
-        };
+    /// Upload glyphs rasterized since the last call, call it before drawing text.
+    /// Texture is recreated, when glyph pages were added or resized, or the cache was cleared.
+    pub fn update_glyphs(&mut self, system: &mut System) {
+        let updates = take_glyph_updates();
+        let page_count = updates.page_count.max(1) as u32;
 
-        let texture = system.device.create_texture(&wgpu::TextureDescriptor {
-            size: texture_extent,
-            array_layer_count: 1,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R8Unorm,
-            usage: wgpu::TextureUsage::COPY_DST | wgpu::TextureUsage::SAMPLED,
-        });
+        let recreate = match &self.glyphs {
+            Some(glyphs) => {
+                glyphs.pages < page_count
+                    || glyphs.page_size != updates.page_size
+                    || glyphs.generation != updates.generation
+            }
+            None => true,
+        };
 
         let mut encoder = system
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
 
-        for (_key, value) in font_instance.characters.iter() {
-            let image_buffer = system
-                .device
-                .create_buffer_mapped(value.data.len(), wgpu::BufferUsage::COPY_SRC)
-                .fill_from_slice(&value.data);
-
-            encoder.copy_buffer_to_texture(
-                wgpu::BufferCopyView {
-                    buffer: &image_buffer,
-                    offset: 0,
-                    row_pitch: value.width as u32,
-                    image_height: value.height as u32,
-                },
-                wgpu::TextureCopyView {
-                    texture: &texture,
-                    mip_level: 0,
-                    array_layer: 0,
-                    origin: wgpu::Origin3d {
-                        x: value.x as f32,
-                        y: value.y as f32,
-                        z: 0.0,
-                    },
-                },
-                wgpu::Extent3d {
-                    width: value.width as u32,
-                    height: value.height as u32,
-                    depth: 1,
-                },
+        if recreate {
+            let glyphs = GlyphTexture::new(
+                system,
+                &self.texture_layout,
+                updates.page_size,
+                page_count,
+                updates.generation,
             );
+            for page in 0..updates.page_count {
+                copy_pixels_to_layer(
+                    system,
+                    &mut encoder,
+                    &glyphs.texture,
+                    page as u32,
+                    0,
+                    (0, 0, updates.page_size, updates.page_size),
+                    &glyph_page(page),
+                    1,
+                );
+            }
+            self.glyphs = Some(glyphs);
+        } else if let Some(glyphs) = &self.glyphs {
+            for upload in updates.uploads.iter() {
+                copy_pixels_to_layer(
+                    system,
+                    &mut encoder,
+                    &glyphs.texture,
+                    upload.page as u32,
+                    0,
+                    (upload.x, upload.y, upload.width, upload.height),
+                    &upload.data,
+                    1,
+                );
+            }
         }
 
-        let texture_view = texture.create_default_view();
-
-        let bind_group = system.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.texture_layout,
-            bindings: &[
-                wgpu::Binding {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::Binding {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
-
         system.queue.submit(&[encoder.finish()]);
+    }
 
-        bind_group
+    /// Glyph atlas for `draw`, call `update_glyphs` first
+    pub fn glyph_texture(&self) -> &wgpu::BindGroup {
+        &self
+            .glyphs
+            .as_ref()
+            .expect("Glyph texture is created by update_glyphs")
+            .bind_group
     }
 
     pub fn draw(
-        &mut self,
+        &self,
+        pass: &mut wgpu::RenderPass,
+        system: &System,
+        indices: &Vec<u16>,
+        vertices: &Vec<Vertex>,
+        textures: &wgpu::BindGroup,
+    ) {
+        self.draw_with(
+            &self.render_pipeline,
+            pass,
+            system,
+            indices,
+            vertices,
+            textures,
+        );
+    }
+
+    /// Draw text built with a distance field font, like `Text::sdf`
+    pub fn draw_sdf(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        system: &System,
+        indices: &Vec<u16>,
+        vertices: &Vec<Vertex>,
+        textures: &wgpu::BindGroup,
+    ) {
+        self.draw_with(
+            &self.sdf_pipeline,
+            pass,
+            system,
+            indices,
+            vertices,
+            textures,
+        );
+    }
+
+    fn draw_with(
+        &self,
+        render_pipeline: &wgpu::RenderPipeline,
         pass: &mut wgpu::RenderPass,
         system: &System,
         indices: &Vec<u16>,
@@ -285,7 +458,7 @@ impl Pipeline {
             .create_buffer_mapped(indices.len(), wgpu::BufferUsage::INDEX)
             .fill_from_slice(&indices);
 
-        pass.set_pipeline(&self.render_pipeline);
+        pass.set_pipeline(render_pipeline);
 
         pass.set_bind_group(0, &self.bind_group, &[]);
         pass.set_bind_group(1, textures, &[]);
@@ -295,6 +468,21 @@ impl Pipeline {
         pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
     }
 
+    /// Outline and glow for `draw_sdf`, used by every distance field text
+    pub fn set_sdf_effects(&mut self, system: &mut System, effects: SdfEffects) {
+        let temp_buf = system
+            .device
+            .create_buffer_mapped(12, wgpu::BufferUsage::COPY_SRC)
+            .fill_from_slice(&effects.to_uniform());
+
+        let mut encoder = system
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        encoder.copy_buffer_to_buffer(&temp_buf, 0, &self.effects_buffer, 0, 48);
+
+        system.queue.submit(&[encoder.finish()]);
+    }
+
     pub fn resize(&mut self, system: &mut System) {
         let screen_matrix = system.get_screen_matrix();
         let mx_ref: &[f32; 16] = screen_matrix.as_ref();
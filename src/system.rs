@@ -1,4 +1,8 @@
-use crate::utils::OPENGL_TO_WGPU_MATRIX;
+use crate::definitions::Texture;
+use crate::utils::assets::{AssetManager, FontAsset, Handle, ReloadError, ShaderAsset};
+use crate::utils::texture::TextureOptions;
+use crate::utils::{ShaderStage, OPENGL_TO_WGPU_MATRIX};
+use collision::Aabb2;
 use winit::window::WindowBuilder;
 
 pub struct System {
@@ -6,6 +10,9 @@ pub struct System {
     pub screen_descriptor: wgpu::SwapChainDescriptor,
     pub queue: wgpu::Queue,
     pub window: winit::window::WindowBuilder,
+    /// Seconds since the previous frame, use it to advance animations
+    pub delta_time: f32,
+    pub assets: AssetManager,
 }
 
 impl System {
@@ -16,6 +23,69 @@ impl System {
         self.screen_descriptor.height = size.height as u32;
     }
 
+    /// Run `f` with assets taken out of the system, loaders need both
+    pub fn with_assets<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut AssetManager, &System) -> R,
+    {
+        let mut assets = std::mem::take(&mut self.assets);
+        let result = f(&mut assets, self);
+        self.assets = assets;
+        result
+    }
+
+    /// Texture array from files, `layout` is `Pipeline::texture_layout`
+    pub fn load_texture_array(
+        &mut self,
+        layout: &wgpu::BindGroupLayout,
+        paths: &[&str],
+        options: TextureOptions,
+    ) -> Handle<Texture> {
+        self.with_assets(|assets, system| assets.load_texture_array(system, layout, paths, options))
+    }
+
+    pub fn load_texture(
+        &mut self,
+        layout: &wgpu::BindGroupLayout,
+        path: &str,
+        options: TextureOptions,
+    ) -> Handle<Texture> {
+        self.load_texture_array(layout, &[path], options)
+    }
+
+    pub fn load_font(&mut self, path: &str) -> Handle<FontAsset> {
+        self.assets.load_font(path)
+    }
+
+    pub fn load_shader(&mut self, path: &str, stage: ShaderStage) -> Handle<ShaderAsset> {
+        self.with_assets(|assets, system| assets.load_shader(system, path, stage))
+    }
+
+    /// Reload texture files and recreate the texture at their size, see `AssetManager::reload_texture`
+    pub fn reload_texture(
+        &mut self,
+        layout: &wgpu::BindGroupLayout,
+        handle: Handle<Texture>,
+    ) -> Result<(), ReloadError> {
+        self.with_assets(|assets, system| assets.reload_texture(system, layout, handle))
+    }
+
+    /// Reload assets changed on disk, called once per frame by the app loop
+    pub fn poll_assets(&mut self) {
+        self.with_assets(|assets, system| assets.poll(system));
+    }
+
+    /// Visible area in screen coordinates, used for culling
+    pub fn screen_rect(&self) -> Aabb2<f32> {
+        Aabb2::new(
+            cgmath::Point2::new(0.0, 0.0),
+            cgmath::Point2::new(
+                self.screen_descriptor.width as f32,
+                self.screen_descriptor.height as f32,
+            ),
+        )
+    }
+
     pub fn get_screen_matrix(&self) -> cgmath::Matrix4<f32> {
         let matrix: cgmath::Matrix4<f32> = cgmath::Ortho::<f32> {
             left: 0.0,
@@ -0,0 +1,266 @@
+use crate::mesh::Image;
+use crate::utils::sprite::{SpriteSheet, UvRect};
+
+//Frames skipped in one update at most, when delta time is huge
+const MAX_STEPS: usize = 1000;
+
+/// Animation frame: texture array layer, optional area on it and how long it is shown
+#[derive(Copy, Clone, Debug)]
+pub struct AnimationFrame {
+    pub layer: i32,
+    pub uv: Option<UvRect>,
+    /// Part of the image box the frame covers, for trimmed sprite frames
+    pub trim: Option<UvRect>,
+    /// Seconds
+    pub duration: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlayMode {
+    Once,
+    Loop,
+    PingPong,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    /// Animation started again from its first frame
+    Looped,
+    /// `PlayMode::Once` animation reached its last frame
+    Finished,
+}
+
+/// Frame based sprite animation, advanced with frame delta time
+#[derive(Clone, Debug)]
+pub struct SpriteAnimation {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode,
+    pub speed: f32,
+    current: usize,
+    elapsed: f32,
+    forward: bool,
+    playing: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl SpriteAnimation {
+    pub fn new(frames: Vec<AnimationFrame>, mode: PlayMode) -> Self {
+        Self {
+            frames,
+            mode,
+            speed: 1.0,
+            current: 0,
+            elapsed: 0.0,
+            forward: true,
+            playing: true,
+            events: Vec::new(),
+        }
+    }
+
+    /// Every layer in `layers` shown for `duration` seconds
+    pub fn from_layers(layers: std::ops::Range<i32>, duration: f32, mode: PlayMode) -> Self {
+        let frames = layers
+            .map(|layer| AnimationFrame {
+                layer,
+                uv: None,
+                trim: None,
+                duration,
+            })
+            .collect();
+        SpriteAnimation::new(frames, mode)
+    }
+
+    /// Consecutive layers from `first_layer`, every one shown for its delay in seconds
+    pub fn from_delays(first_layer: i32, delays: &[f32], mode: PlayMode) -> Self {
+        let frames = delays
+            .iter()
+            .enumerate()
+            .map(|(index, duration)| AnimationFrame {
+                layer: first_layer + index as i32,
+                uv: None,
+                trim: None,
+                duration: *duration,
+            })
+            .collect();
+        SpriteAnimation::new(frames, mode)
+    }
+
+    /// Every sprite sheet frame, in index order, shown for `duration` seconds
+    pub fn from_sheet(sheet: &SpriteSheet, duration: f32, mode: PlayMode) -> Self {
+        let frames = sheet
+            .frames
+            .iter()
+            .map(|frame| AnimationFrame {
+                layer: frame.layer,
+                uv: Some(frame.uv),
+                trim: Some(frame.trim),
+                duration,
+            })
+            .collect();
+        SpriteAnimation::new(frames, mode)
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Go back to the first frame and play again
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.playing = true;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.frames.get(self.current)
+    }
+
+    /// Events from the last `update`
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    pub fn looped(&self) -> bool {
+        self.events.contains(&AnimationEvent::Looped)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.events.contains(&AnimationEvent::Finished)
+    }
+
+    //Move to the next frame, returns false when the animation stopped
+    fn step(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            PlayMode::Loop => {
+                if self.current < last {
+                    self.current += 1;
+                } else {
+                    self.current = 0;
+                    self.events.push(AnimationEvent::Looped);
+                }
+            }
+            PlayMode::Once => {
+                if self.current < last {
+                    self.current += 1;
+                } else {
+                    self.playing = false;
+                    self.events.push(AnimationEvent::Finished);
+                    return false;
+                }
+            }
+            PlayMode::PingPong => {
+                if last == 0 {
+                    self.events.push(AnimationEvent::Looped);
+                    return true;
+                }
+
+                //Turn around at the ends, one loop ends back at the first frame
+                if self.forward && self.current == last {
+                    self.forward = false;
+                } else if !self.forward && self.current == 0 {
+                    self.forward = true;
+                }
+
+                if self.forward {
+                    self.current += 1;
+                } else {
+                    self.current -= 1;
+                    if self.current == 0 {
+                        self.events.push(AnimationEvent::Looped);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Advance animation by `delta_time` seconds
+    pub fn update(&mut self, delta_time: f32) {
+        self.events.clear();
+        if !self.playing || self.frames.is_empty() {
+            return;
+        }
+
+        self.elapsed += delta_time * self.speed;
+        for _ in 0..MAX_STEPS {
+            let duration = self.frames[self.current].duration;
+            if self.elapsed < duration {
+                return;
+            }
+            self.elapsed -= duration;
+            if !self.step() {
+                self.elapsed = 0.0;
+                return;
+            }
+        }
+        self.elapsed = 0.0;
+    }
+
+    /// Show current frame on the image, rebuild the image afterwards
+    pub fn apply(&self, image: &mut Image) {
+        if let Some(frame) = self.current_frame() {
+            image.texture = frame.layer;
+            //Frames without an area show the whole layer
+            image.uv = frame.uv.unwrap_or_default();
+            image.trim = frame.trim.unwrap_or_default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Frame index and looped flag after every one second update
+    fn play(frames: i32, updates: usize) -> Vec<(usize, bool)> {
+        let mut animation = SpriteAnimation::from_layers(0..frames, 1.0, PlayMode::PingPong);
+        (0..updates)
+            .map(|_| {
+                animation.update(1.0);
+                (animation.current_index(), animation.looped())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ping_pong_with_one_frame_loops_every_frame() {
+        assert_eq!(play(1, 3), vec![(0, true), (0, true), (0, true)]);
+    }
+
+    #[test]
+    fn ping_pong_with_two_frames_loops() {
+        assert_eq!(
+            play(2, 4),
+            vec![(1, false), (0, true), (1, false), (0, true)]
+        );
+    }
+
+    #[test]
+    fn ping_pong_loops_at_the_first_frame() {
+        assert_eq!(
+            play(3, 6),
+            vec![
+                (1, false),
+                (2, false),
+                (1, false),
+                (0, true),
+                (1, false),
+                (2, false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use crate::definitions::Texture;
+use crate::utils::font::{load_font_bytes, register_font_name, reload_font_bytes, FontId};
+use crate::utils::texture::TextureOptions;
+use crate::utils::{try_load_glsl, ShaderStage};
+use crate::System;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+//Seconds between file modification checks
+const POLL_INTERVAL: f32 = 0.5;
+
+/// Typed asset id, returned by `System::load_*` functions
+#[derive(Debug)]
+pub struct Handle<T> {
+    id: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T> Handle<T> {
+    fn new(id: usize) -> Self {
+        Self {
+            id,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle::new(self.id)
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Truetype font file, registered for text elements
+#[derive(Debug, Clone)]
+pub struct FontAsset {
+    pub id: FontId,
+    pub bytes: Vec<u8>,
+    /// Increased on every reload, rebuild text when it changes
+    pub version: u32,
+}
+
+/// Compiled GLSL shader, pipelines using it have to be recreated after reload
+#[derive(Debug)]
+pub struct ShaderAsset {
+    pub stage: ShaderStage,
+    pub spirv: Vec<u32>,
+    pub module: wgpu::ShaderModule,
+    pub version: u32,
+}
+
+/// Asset file, that could not be reloaded, the previous version is kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed to reload {:?}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+#[derive(Debug)]
+struct Entry<T> {
+    key: String,
+    paths: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+    refs: usize,
+    asset: T,
+}
+
+#[derive(Debug)]
+struct Store<T> {
+    entries: HashMap<usize, Entry<T>>,
+    keys: HashMap<String, usize>,
+}
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Store<T> {
+    //Existing asset for the key, with one more reference
+    fn acquire(&mut self, key: &str) -> Option<Handle<T>> {
+        let id = *self.keys.get(key)?;
+        self.entries.get_mut(&id).unwrap().refs += 1;
+        Some(Handle::new(id))
+    }
+
+    fn insert(&mut self, id: usize, key: String, paths: Vec<PathBuf>, asset: T) -> Handle<T> {
+        let modified = paths.iter().map(|path| modified(path)).collect();
+        self.keys.insert(key.clone(), id);
+        self.entries.insert(
+            id,
+            Entry {
+                key,
+                paths,
+                modified,
+                refs: 1,
+                asset,
+            },
+        );
+        Handle::new(id)
+    }
+
+    fn release(&mut self, handle: Handle<T>) -> bool {
+        let remove = match self.entries.get_mut(&handle.id) {
+            Some(entry) => {
+                entry.refs -= 1;
+                entry.refs == 0
+            }
+            None => return false,
+        };
+
+        if remove {
+            let entry = self.entries.remove(&handle.id).unwrap();
+            self.keys.remove(&entry.key);
+        }
+        true
+    }
+
+    //Ids of assets with a changed file, updates stored modification times
+    fn changed(&mut self) -> Vec<usize> {
+        let mut changed = Vec::new();
+        for (id, entry) in self.entries.iter_mut() {
+            let modified: Vec<Option<SystemTime>> =
+                entry.paths.iter().map(|path| modified(path)).collect();
+            if modified != entry.modified {
+                entry.modified = modified;
+                changed.push(*id);
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+fn absolute(path: &str) -> PathBuf {
+    std::env::current_dir().expect("Bad asset path").join(path)
+}
+
+//Texture array layers from files, all of the same size
+fn open_layers(paths: &[PathBuf]) -> Result<Vec<RgbaImage>, ReloadError> {
+    let mut layers: Vec<RgbaImage> = Vec::new();
+    for path in paths {
+        let layer = image::open(path)
+            .map_err(|error| ReloadError {
+                path: path.clone(),
+                message: error.to_string(),
+            })?
+            .to_rgba();
+
+        if let Some(first) = layers.first() {
+            if first.dimensions() != layer.dimensions() {
+                return Err(ReloadError {
+                    path: path.clone(),
+                    message: "Texture array layers must have the same size".to_string(),
+                });
+            }
+        }
+        layers.push(layer);
+    }
+    Ok(layers)
+}
+
+/// Loaded textures, fonts and shaders, shared by path and reference counted.
+/// Files are polled for changes and reloaded in place, handles stay valid.
+#[derive(Debug)]
+pub struct AssetManager {
+    pub hot_reload: bool,
+    next_id: usize,
+    since_poll: f32,
+    textures: Store<Texture>,
+    fonts: Store<FontAsset>,
+    shaders: Store<ShaderAsset>,
+    reloaded: Vec<usize>,
+    errors: Vec<ReloadError>,
+}
+
+impl Default for AssetManager {
+    fn default() -> Self {
+        Self {
+            hot_reload: true,
+            next_id: 0,
+            since_poll: 0.0,
+            textures: Store::default(),
+            fonts: Store::default(),
+            shaders: Store::default(),
+            reloaded: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl AssetManager {
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Texture array, one layer per path, in the same order.
+    /// Same paths and options share one texture.
+    pub fn load_texture_array(
+        &mut self,
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        paths: &[&str],
+        options: TextureOptions,
+    ) -> Handle<Texture> {
+        let key = format!("{}|{:?}", paths.join("|"), options);
+        if let Some(handle) = self.textures.acquire(&key) {
+            return handle;
+        }
+
+        let paths: Vec<PathBuf> = paths.iter().map(|path| absolute(path)).collect();
+        let layers: Vec<RgbaImage> = paths
+            .iter()
+            .map(|path| image::open(path).expect("Failed to open image").to_rgba())
+            .collect();
+        let (width, height) = layers
+            .first()
+            .map(|layer| layer.dimensions())
+            .unwrap_or((1, 1));
+
+        let mut texture = Texture::new(system, layout, width, height, layers, options);
+        texture.name = key.clone();
+
+        let id = self.next_id();
+        self.textures.insert(id, key, paths, texture)
+    }
+
+    pub fn load_texture(
+        &mut self,
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        path: &str,
+        options: TextureOptions,
+    ) -> Handle<Texture> {
+        self.load_texture_array(system, layout, &[path], options)
+    }
+
+    pub fn load_font(&mut self, path: &str) -> Handle<FontAsset> {
+        if let Some(handle) = self.fonts.acquire(path) {
+            return handle;
+        }
+
+        let absolute_path = absolute(path);
+        let bytes = std::fs::read(&absolute_path).expect("failed to read font");
+        let font = load_font_bytes(&bytes);
+        register_font_name(path, font);
+
+        let id = self.next_id();
+        self.fonts.insert(
+            id,
+            path.to_string(),
+            vec![absolute_path],
+            FontAsset {
+                id: font,
+                bytes,
+                version: 0,
+            },
+        )
+    }
+
+    /// GLSL shader, panics when the first compilation fails
+    pub fn load_shader(
+        &mut self,
+        system: &System,
+        path: &str,
+        stage: ShaderStage,
+    ) -> Handle<ShaderAsset> {
+        if let Some(handle) = self.shaders.acquire(path) {
+            return handle;
+        }
+
+        let absolute_path = absolute(path);
+        let code = std::fs::read_to_string(&absolute_path).expect("failed to read shader");
+        let spirv = try_load_glsl(&code, stage).expect("Failed to compile shader");
+        let module = system.device.create_shader_module(&spirv);
+
+        let id = self.next_id();
+        self.shaders.insert(
+            id,
+            path.to_string(),
+            vec![absolute_path],
+            ShaderAsset {
+                stage,
+                spirv,
+                module,
+                version: 0,
+            },
+        )
+    }
+
+    pub fn texture(&self, handle: Handle<Texture>) -> Option<&Texture> {
+        self.textures
+            .entries
+            .get(&handle.id)
+            .map(|entry| &entry.asset)
+    }
+
+    pub fn texture_mut(&mut self, handle: Handle<Texture>) -> Option<&mut Texture> {
+        self.textures
+            .entries
+            .get_mut(&handle.id)
+            .map(|entry| &mut entry.asset)
+    }
+
+    /// Bind group for `Pipeline::draw`, it changes when layers are added
+    pub fn bind_group(&self, handle: Handle<Texture>) -> Option<&wgpu::BindGroup> {
+        self.texture(handle).map(|texture| &texture.bind_group)
+    }
+
+    pub fn font(&self, handle: Handle<FontAsset>) -> Option<&FontAsset> {
+        self.fonts.entries.get(&handle.id).map(|entry| &entry.asset)
+    }
+
+    pub fn shader(&self, handle: Handle<ShaderAsset>) -> Option<&ShaderAsset> {
+        self.shaders
+            .entries
+            .get(&handle.id)
+            .map(|entry| &entry.asset)
+    }
+
+    /// Drop one reference, asset is freed with the last one
+    pub fn release_texture(&mut self, handle: Handle<Texture>) -> bool {
+        self.textures.release(handle)
+    }
+
+    pub fn release_font(&mut self, handle: Handle<FontAsset>) -> bool {
+        self.fonts.release(handle)
+    }
+
+    pub fn release_shader(&mut self, handle: Handle<ShaderAsset>) -> bool {
+        self.shaders.release(handle)
+    }
+
+    /// True when the asset was reloaded in the last poll
+    pub fn was_reloaded<T>(&self, handle: Handle<T>) -> bool {
+        self.reloaded.contains(&handle.id)
+    }
+
+    /// Files, that failed to reload in the last poll
+    pub fn reload_errors(&self) -> &[ReloadError] {
+        &self.errors
+    }
+
+    /// Check file modification times every `POLL_INTERVAL` seconds and reload changed assets
+    pub fn poll(&mut self, system: &System) {
+        self.reloaded.clear();
+        self.errors.clear();
+        self.since_poll += system.delta_time;
+        if !self.hot_reload || self.since_poll < POLL_INTERVAL {
+            return;
+        }
+        self.since_poll = 0.0;
+        self.reload_changed(system);
+    }
+
+    /// Reload texture files now and recreate the texture at their current size,
+    /// `layout` is `Pipeline::texture_layout`. `bind_group` of the texture changes.
+    pub fn reload_texture(
+        &mut self,
+        system: &System,
+        layout: &wgpu::BindGroupLayout,
+        handle: Handle<Texture>,
+    ) -> Result<(), ReloadError> {
+        let entry = match self.textures.entries.get_mut(&handle.id) {
+            Some(entry) => entry,
+            None => {
+                return Err(ReloadError {
+                    path: PathBuf::new(),
+                    message: "Texture was released".to_string(),
+                })
+            }
+        };
+
+        let layers = open_layers(&entry.paths)?;
+        let (width, height) = layers
+            .first()
+            .map(|layer| layer.dimensions())
+            .unwrap_or((1, 1));
+
+        let mut texture = Texture::new(system, layout, width, height, layers, entry.asset.options);
+        texture.name = entry.asset.name.clone();
+        entry.asset = texture;
+        entry.modified = entry.paths.iter().map(|path| modified(path)).collect();
+        self.reloaded.push(handle.id);
+        Ok(())
+    }
+
+    /// Reload every asset, whose file changed since it was loaded.
+    /// Broken files are kept in the previous version and listed in `reload_errors`.
+    /// Textures, that changed size, are reported too, reload them with `reload_texture`.
+    pub fn reload_changed(&mut self, system: &System) {
+        for id in self.textures.changed() {
+            let entry = self.textures.entries.get_mut(&id).unwrap();
+            let texture = &mut entry.asset;
+
+            let layers = match open_layers(&entry.paths) {
+                Ok(layers) => layers,
+                Err(error) => {
+                    self.errors.push(error);
+                    continue;
+                }
+            };
+
+            if let Some(layer) = layers.first() {
+                if layer.dimensions() != (texture.width, texture.height) {
+                    let (width, height) = layer.dimensions();
+                    self.errors.push(ReloadError {
+                        path: entry.paths[0].clone(),
+                        message: format!(
+                            "Texture size changed from {}x{} to {}x{}, use reload_texture",
+                            texture.width, texture.height, width, height
+                        ),
+                    });
+                    continue;
+                }
+            }
+
+            for (layer, image) in layers.into_iter().enumerate() {
+                texture.update_layer(system, layer, &image.into_raw());
+            }
+            self.reloaded.push(id);
+        }
+
+        for id in self.fonts.changed() {
+            let entry = self.fonts.entries.get_mut(&id).unwrap();
+            let font = entry.asset.id;
+            let bytes = std::fs::read(&entry.paths[0])
+                .map_err(|error| error.to_string())
+                .and_then(|bytes| reload_font_bytes(font, &bytes).map(|_| bytes));
+            match bytes {
+                Ok(bytes) => {
+                    entry.asset.bytes = bytes;
+                    entry.asset.version += 1;
+                    self.reloaded.push(id);
+                }
+                Err(message) => self.errors.push(ReloadError {
+                    path: entry.paths[0].clone(),
+                    message,
+                }),
+            }
+        }
+
+        for id in self.shaders.changed() {
+            let entry = self.shaders.entries.get_mut(&id).unwrap();
+            let spirv = std::fs::read_to_string(&entry.paths[0])
+                .map_err(|error| error.to_string())
+                .and_then(|code| try_load_glsl(&code, entry.asset.stage));
+            match spirv {
+                Ok(spirv) => {
+                    entry.asset.module = system.device.create_shader_module(&spirv);
+                    entry.asset.spirv = spirv;
+                    entry.asset.version += 1;
+                    self.reloaded.push(id);
+                }
+                Err(message) => self.errors.push(ReloadError {
+                    path: entry.paths[0].clone(),
+                    message,
+                }),
+            }
+        }
+    }
+}
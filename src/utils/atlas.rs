@@ -0,0 +1,420 @@
+use crate::utils::sprite::{Frame, UvRect};
+use image::RgbaImage;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Skyline bottom-left rectangle packer, places rectangles on one page
+#[derive(Clone, Debug)]
+pub struct SkylinePacker {
+    pub width: u32,
+    pub height: u32,
+    pub padding: u32,
+    skyline: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            skyline: vec![SkylineNode { x: 0, y: 0, width }],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.skyline = vec![SkylineNode {
+            x: 0,
+            y: 0,
+            width: self.width,
+        }];
+    }
+
+    /// Highest used row on the page
+    pub fn used_height(&self) -> u32 {
+        self.skyline.iter().map(|node| node.y).max().unwrap_or(0)
+    }
+
+    //Lowest y, where a rectangle fits starting at node `index`
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let (mut y, mut remaining) = (0, width as i64);
+        for node in self.skyline[index..].iter() {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(node.y);
+            if y + height > self.height {
+                return None;
+            }
+            remaining -= node.width as i64;
+        }
+        Some(y)
+    }
+
+    /// Find place for a rectangle, returns its top left corner.
+    /// Padding is kept on the right and bottom side of every rectangle.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (padded_w, padded_h) = (width + self.padding, height + self.padding);
+
+        //Lowest top edge wins, narrower node breaks ties
+        let mut best: Option<(usize, u32)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fit(index, padded_w, padded_h) {
+                let better = match best {
+                    Some((best_index, best_y)) => {
+                        y < best_y
+                            || (y == best_y
+                                && self.skyline[index].width < self.skyline[best_index].width)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some((index, y));
+                }
+            }
+        }
+
+        let (index, y) = best?;
+        let x = self.skyline[index].x;
+        self.skyline.insert(
+            index,
+            SkylineNode {
+                x,
+                y: y + padded_h,
+                width: padded_w,
+            },
+        );
+
+        //Cut nodes, that are now under the new one
+        let right = x + padded_w;
+        let next = index + 1;
+        while next < self.skyline.len() {
+            let node = self.skyline[next];
+            if node.x >= right {
+                break;
+            }
+            let node_right = node.x + node.width;
+            if node_right <= right {
+                self.skyline.remove(next);
+            } else {
+                self.skyline[next].x = right;
+                self.skyline[next].width = node_right - right;
+                break;
+            }
+        }
+
+        //Merge neighbours on the same height
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+
+        Some((x, y))
+    }
+}
+
+/// Rectangle place on atlas pages
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PackedRect {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Pack rectangles to as many pages as needed, bigger rectangles go first.
+/// Returns places in the same order as `sizes`, None when a rectangle is bigger than a page.
+pub fn pack_rects(
+    sizes: &[(u32, u32)],
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+) -> (Vec<Option<PackedRect>>, usize) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (sizes[*a], sizes[*b]);
+        (b.1, b.0).cmp(&(a.1, a.0))
+    });
+
+    let mut pages: Vec<SkylinePacker> = Vec::new();
+    let mut places = vec![None; sizes.len()];
+
+    for index in order {
+        let (width, height) = sizes[index];
+        if width + padding > page_width || height + padding > page_height {
+            continue;
+        }
+
+        let mut place = None;
+        for (page, packer) in pages.iter_mut().enumerate() {
+            if let Some((x, y)) = packer.pack(width, height) {
+                place = Some((page, x, y));
+                break;
+            }
+        }
+
+        let (page, x, y) = match place {
+            Some(place) => place,
+            None => {
+                let mut packer = SkylinePacker::new(page_width, page_height, padding);
+                let (x, y) = packer.pack(width, height).unwrap();
+                pages.push(packer);
+                (pages.len() - 1, x, y)
+            }
+        };
+
+        places[index] = Some(PackedRect {
+            page,
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    (places, pages.len())
+}
+
+/// Returned by `AtlasBuilder::build`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AtlasError {
+    /// Image with the index from `AtlasBuilder::add` does not fit on a page
+    ImageTooBig {
+        index: usize,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AtlasError::ImageTooBig {
+                index,
+                width,
+                height,
+            } => write!(
+                f,
+                "Image {} ({}x{}) is bigger than the atlas page",
+                index, width, height
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+/// Packed atlas pages, ready for `create_atlas` in the default pipeline
+pub struct Atlas {
+    pub width: u32,
+    pub height: u32,
+    pub pages: Vec<RgbaImage>,
+    /// Image places, in the order images were added
+    pub frames: Vec<Frame>,
+}
+
+/// Collects images of different sizes and packs them to atlas pages
+pub struct AtlasBuilder {
+    pub page_width: u32,
+    pub page_height: u32,
+    pub padding: u32,
+    images: Vec<RgbaImage>,
+}
+
+impl AtlasBuilder {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            page_width,
+            page_height,
+            padding: 2,
+            images: Vec::new(),
+        }
+    }
+
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Add image, returns its index in `Atlas::frames`
+    pub fn add(&mut self, image: RgbaImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    /// Add image from path, relative to the current directory
+    pub fn add_path(&mut self, path: &str) -> usize {
+        let absolute_path = std::env::current_dir().expect("Bad image path");
+        let image = image::open(absolute_path.join(path))
+            .expect("Failed to open image")
+            .to_rgba();
+        self.add(image)
+    }
+
+    /// Pack all images, fails when an image with padding is bigger than a page
+    pub fn build(&self) -> Result<Atlas, AtlasError> {
+        let sizes: Vec<(u32, u32)> = self.images.iter().map(|image| image.dimensions()).collect();
+        let (places, page_count) =
+            pack_rects(&sizes, self.page_width, self.page_height, self.padding);
+
+        if let Some(index) = places.iter().position(|place| place.is_none()) {
+            let (width, height) = sizes[index];
+            return Err(AtlasError::ImageTooBig {
+                index,
+                width,
+                height,
+            });
+        }
+
+        let mut pages: Vec<RgbaImage> = (0..page_count)
+            .map(|_| RgbaImage::new(self.page_width, self.page_height))
+            .collect();
+
+        let frames = places
+            .iter()
+            .zip(self.images.iter())
+            .map(|(place, image)| {
+                let place = place.unwrap();
+                image::imageops::replace(&mut pages[place.page], image, place.x, place.y);
+
+                Frame {
+                    layer: place.page as i32,
+                    uv: UvRect::from_texels(
+                        place.x as f32,
+                        place.y as f32,
+                        place.width as f32,
+                        place.height as f32,
+                        self.page_width as f32,
+                        self.page_height as f32,
+                    ),
+                    width: place.width as f32,
+                    height: place.height as f32,
+                    trim: UvRect::default(),
+                }
+            })
+            .collect();
+
+        Ok(Atlas {
+            width: self.page_width,
+            height: self.page_height,
+            pages,
+            frames,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &PackedRect, b: &PackedRect) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.width
+            && b.x < a.x + a.width
+            && a.y < b.y + b.height
+            && b.y < a.y + a.height
+    }
+
+    //Mixed sizes, deterministic
+    fn sizes() -> Vec<(u32, u32)> {
+        (0..200u32)
+            .map(|i| (1 + (i * 37) % 61, 1 + (i * 53) % 47))
+            .collect()
+    }
+
+    #[test]
+    fn skyline_packer_keeps_rects_apart() {
+        let mut packer = SkylinePacker::new(256, 256, 1);
+        let mut packed = Vec::new();
+        for (width, height) in sizes() {
+            if let Some((x, y)) = packer.pack(width, height) {
+                packed.push(PackedRect {
+                    page: 0,
+                    x,
+                    y,
+                    width,
+                    height,
+                });
+            }
+        }
+
+        assert!(!packed.is_empty());
+        for (i, a) in packed.iter().enumerate() {
+            assert!(a.x + a.width <= 256 && a.y + a.height <= 256);
+            for b in packed[i + 1..].iter() {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn skyline_packer_rejects_oversized() {
+        let mut packer = SkylinePacker::new(64, 64, 2);
+        assert_eq!(packer.pack(65, 10), None);
+        assert_eq!(packer.pack(63, 10), None);
+        assert_eq!(packer.pack(62, 62), Some((0, 0)));
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn pack_rects_places_everything_on_pages() {
+        let sizes = sizes();
+        let (places, pages) = pack_rects(&sizes, 128, 128, 2);
+
+        assert!(pages > 1);
+        let places: Vec<PackedRect> = places.into_iter().map(|place| place.unwrap()).collect();
+        for (i, a) in places.iter().enumerate() {
+            assert_eq!((a.width, a.height), sizes[i]);
+            assert!(a.page < pages);
+            assert!(a.x + a.width + 2 <= 128 && a.y + a.height + 2 <= 128);
+            for b in places[i + 1..].iter() {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn pack_rects_rejects_oversized() {
+        let (places, pages) = pack_rects(&[(10, 10), (200, 10), (10, 127)], 128, 128, 2);
+        assert!(places[0].is_some());
+        assert_eq!(places[1], None);
+        assert_eq!(places[2], None);
+        assert_eq!(pages, 1);
+    }
+
+    #[test]
+    fn build_reports_oversized_image() {
+        let mut builder = AtlasBuilder::new(64, 64);
+        builder.add(RgbaImage::new(16, 16));
+        builder.add(RgbaImage::new(100, 16));
+
+        match builder.build() {
+            Err(error) => assert_eq!(
+                error,
+                AtlasError::ImageTooBig {
+                    index: 1,
+                    width: 100,
+                    height: 16
+                }
+            ),
+            Ok(_) => panic!("Oversized image was packed"),
+        }
+    }
+}
@@ -1,9 +1,18 @@
-use crate::definitions::{Mesh, MeshTrait};
+use crate::definitions::{Mesh, MeshTrait, Vertex};
+use collision::Aabb2;
+
+/// Culling results, elements added to the batch and elements skipped as offscreen
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
 
 #[derive(Clone)]
 pub struct Batch<T: Clone> {
     pub indices: Vec<u16>,
     pub vertices: Vec<T>,
+    pub stats: CullStats,
 }
 
 impl<T: Clone> Batch<T> {
@@ -11,12 +20,14 @@ impl<T: Clone> Batch<T> {
         Self {
             indices: Vec::new(),
             vertices: Vec::new(),
+            stats: CullStats::default(),
         }
     }
 
     pub fn clear(&mut self) {
         self.vertices.clear();
         self.indices.clear();
+        self.stats = CullStats::default();
     }
 
     pub fn add(&mut self, mesh: &mut Mesh<T>) {
@@ -28,3 +39,26 @@ impl<T: Clone> Batch<T> {
         self.vertices.extend(mesh.get_vertices());
     }
 }
+
+impl Batch<Vertex> {
+    /// Add mesh only if it overlaps the visible `view` rect, returns true when added
+    pub fn add_visible(&mut self, mesh: &mut Mesh<Vertex>, view: &Aabb2<f32>) -> bool {
+        let visible = match mesh.bounds() {
+            Some(bounds) => {
+                bounds.min.x <= view.max.x
+                    && bounds.max.x >= view.min.x
+                    && bounds.min.y <= view.max.y
+                    && bounds.max.y >= view.min.y
+            }
+            None => false,
+        };
+
+        if visible {
+            self.add(mesh);
+            self.stats.drawn += 1;
+        } else {
+            self.stats.culled += 1;
+        }
+        visible
+    }
+}
@@ -0,0 +1,212 @@
+use crate::definitions::Vertex;
+use crate::utils::transform::Transform;
+use cgmath::{Matrix3, Point2, SquareMatrix};
+use collision::{prelude::*, Aabb2};
+
+/// Exact element shape, in untransformed element space.
+#[derive(Clone, Debug)]
+pub enum Shape {
+    /// The element box itself
+    Rectangle,
+    RoundedRectangle { radius: f32 },
+    Circle { center: Point2<f32>, radius: f32 },
+    Polygon(Vec<Point2<f32>>),
+    /// Triangle list, for elements without a simple shape
+    Mesh {
+        positions: Vec<Point2<f32>>,
+        indices: Vec<u16>,
+    },
+}
+
+//Triangles with a smaller doubled area have no inside
+const DEGENERATE_AREA: f32 = 1e-6;
+
+fn cross(a: Point2<f32>, b: Point2<f32>, point: Point2<f32>) -> f32 {
+    (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x)
+}
+
+fn triangle_contains(
+    a: Point2<f32>,
+    b: Point2<f32>,
+    c: Point2<f32>,
+    point: Point2<f32>,
+) -> bool {
+    //All signs would be zero and every point would count as inside
+    if cross(a, b, c).abs() < DEGENERATE_AREA {
+        return false;
+    }
+    let (d1, d2, d3) = (cross(a, b, point), cross(b, c, point), cross(c, a, point));
+    let negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(negative && positive)
+}
+
+impl Shape {
+    /// Exact check, `point` and `bounds` are in untransformed element space
+    pub fn contains(&self, bounds: &Aabb2<f32>, point: Point2<f32>) -> bool {
+        match self {
+            Shape::Rectangle => bounds.contains(&point),
+            Shape::RoundedRectangle { radius } => {
+                if !bounds.contains(&point) {
+                    return false;
+                }
+                let (half_w, half_h) = (
+                    (bounds.max.x - bounds.min.x) / 2.0,
+                    (bounds.max.y - bounds.min.y) / 2.0,
+                );
+                let radius = radius.min(half_w).min(half_h);
+
+                //Distance from the inner rectangle, that has no rounded corners
+                let dx = ((point.x - (bounds.min.x + half_w)).abs() - (half_w - radius)).max(0.0);
+                let dy = ((point.y - (bounds.min.y + half_h)).abs() - (half_h - radius)).max(0.0);
+                dx * dx + dy * dy <= radius * radius
+            }
+            Shape::Circle { center, radius } => {
+                let (dx, dy) = (point.x - center.x, point.y - center.y);
+                dx * dx + dy * dy <= radius * radius
+            }
+            Shape::Polygon(points) => {
+                //Even-odd rule
+                let mut inside = false;
+                let mut previous = match points.last() {
+                    Some(last) => *last,
+                    None => return false,
+                };
+                for current in points.iter() {
+                    if (current.y > point.y) != (previous.y > point.y)
+                        && point.x
+                            < (previous.x - current.x) * (point.y - current.y)
+                                / (previous.y - current.y)
+                                + current.x
+                    {
+                        inside = !inside;
+                    }
+                    previous = *current;
+                }
+                inside
+            }
+            Shape::Mesh { positions, indices } => indices.chunks(3).any(|triangle| {
+                triangle.len() == 3
+                    && triangle_contains(
+                        positions[triangle[0] as usize],
+                        positions[triangle[1] as usize],
+                        positions[triangle[2] as usize],
+                        point,
+                    )
+            }),
+        }
+    }
+}
+
+/// Element collider, keeps the element box and shape in local space
+/// and the inverse transform for point checks.
+#[derive(Clone, Debug)]
+pub struct Collider {
+    /// World space bounding box, for broad phase checks
+    pub bounds: Aabb2<f32>,
+    /// Untransformed element box
+    pub local: Aabb2<f32>,
+    /// Element box corners in world space
+    pub corners: [Point2<f32>; 4],
+    pub shape: Shape,
+    inverse: Matrix3<f32>,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        let zero = Aabb2::new(Point2::new(0.0, 0.0), Point2::new(0.0, 0.0));
+        Self {
+            bounds: zero,
+            local: zero,
+            corners: [Point2::new(0.0, 0.0); 4],
+            shape: Shape::Rectangle,
+            inverse: Matrix3::identity(),
+        }
+    }
+}
+
+impl Collider {
+    pub fn new(local: Aabb2<f32>, transform: &Transform) -> Self {
+        Collider::from_matrix(local, transform.matrix(&local), Shape::Rectangle)
+    }
+
+    fn from_matrix(local: Aabb2<f32>, matrix: Matrix3<f32>, shape: Shape) -> Self {
+        let corners = [
+            Point2::new(local.min.x, local.min.y),
+            Point2::new(local.max.x, local.min.y),
+            Point2::new(local.max.x, local.max.y),
+            Point2::new(local.min.x, local.max.y),
+        ];
+        let corners = [
+            Transform::transform_point(&matrix, corners[0]),
+            Transform::transform_point(&matrix, corners[1]),
+            Transform::transform_point(&matrix, corners[2]),
+            Transform::transform_point(&matrix, corners[3]),
+        ];
+
+        let mut bounds = Aabb2::new(corners[0], corners[1]);
+        bounds = bounds.grow(corners[2]).grow(corners[3]);
+
+        Self {
+            bounds,
+            local,
+            corners,
+            shape,
+            //Collapsed boxes can't be inverted, they also can't contain anything
+            inverse: matrix.invert().unwrap_or_else(Matrix3::identity),
+        }
+    }
+
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Mesh collider from untransformed vertices, the point-in-triangle fallback.
+    /// `local` is the element box, used as the transform pivot.
+    pub fn from_mesh(
+        local: Aabb2<f32>,
+        transform: &Transform,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) -> Self {
+        let positions: Vec<Point2<f32>> = vertices
+            .iter()
+            .map(|vertex| Point2::new(vertex.in_position[0], vertex.in_position[1]))
+            .collect();
+
+        //Mesh can be bigger than the element box, like text overflowing its width
+        let mesh_bounds = positions
+            .iter()
+            .fold(Aabb2::new(local.min, local.min), |bounds, point| {
+                bounds.grow(*point)
+            });
+
+        Collider::from_matrix(
+            mesh_bounds,
+            transform.matrix(&local),
+            Shape::Mesh {
+                positions,
+                indices: indices.to_vec(),
+            },
+        )
+    }
+
+    /// Move a world space point to the untransformed element space
+    pub fn to_local(&self, point: Point2<f32>) -> Point2<f32> {
+        Transform::transform_point(&self.inverse, point)
+    }
+
+    /// Broad phase check against the world space bounding box
+    pub fn bounds_contains(&self, point: Point2<f32>) -> bool {
+        self.bounds.contains(&point)
+    }
+
+    /// Exact check against the element shape
+    pub fn contains(&self, point: Point2<f32>) -> bool {
+        if !self.bounds_contains(point) {
+            return false;
+        }
+        self.shape.contains(&self.local, self.to_local(point))
+    }
+}
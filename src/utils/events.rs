@@ -1,7 +1,7 @@
 use cgmath::Point2;
 use std::collections::HashSet;
 use winit::event::MouseButton as Button;
-use winit::event::{ElementState, MouseScrollDelta, WindowEvent};
+use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode, WindowEvent};
 
 #[derive(Debug)]
 pub struct Mouse {
@@ -60,6 +60,42 @@ impl Keyboard {
             keys_pressed: HashSet::new(),
         }
     }
+
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&(key as usize))
+    }
+
+    pub fn shift(&self) -> bool {
+        self.is_pressed(VirtualKeyCode::LShift) || self.is_pressed(VirtualKeyCode::RShift)
+    }
+}
+
+/// Text editing input of one event, for `TextInput`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextChar {
+    Char(char),
+    Back,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Enter,
+}
+
+impl TextChar {
+    fn from_key(key: VirtualKeyCode) -> Option<TextChar> {
+        match key {
+            VirtualKeyCode::Back => Some(TextChar::Back),
+            VirtualKeyCode::Delete => Some(TextChar::Delete),
+            VirtualKeyCode::Left => Some(TextChar::Left),
+            VirtualKeyCode::Right => Some(TextChar::Right),
+            VirtualKeyCode::Home => Some(TextChar::Home),
+            VirtualKeyCode::End => Some(TextChar::End),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(TextChar::Enter),
+            _ => None,
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -67,6 +103,8 @@ impl Keyboard {
 pub struct Event {
     pub mouse: Mouse,
     pub keyboard: Keyboard,
+    /// Typed characters and editing keys, cleared after every update
+    pub text: Vec<TextChar>,
     pub dpi_factor: f64,
     pub resized: bool,
 }
@@ -76,6 +114,7 @@ impl Event {
         Event {
             mouse: Mouse::new(),
             keyboard: Keyboard::new(),
+            text: Vec::new(),
             dpi_factor: 1.0,
             resized: false,
         }
@@ -88,9 +127,9 @@ impl Event {
                     match input.state {
                         ElementState::Pressed => {
                             self.keyboard.keys_pressed.insert(keycode as usize);
-                            // if let VirtualKeyCode::Back = keycode {
-                            //     self.text.push(TextChar::Back);
-                            // }
+                            if let Some(text_char) = TextChar::from_key(keycode) {
+                                self.text.push(text_char);
+                            }
                         }
                         ElementState::Released => {
                             self.keyboard.keys_pressed.remove(&(keycode as usize));
@@ -98,10 +137,11 @@ impl Event {
                     }
                 }
             }
-            WindowEvent::ReceivedCharacter(_c) => {
-                // if c != '\x08' && c != '\r' && c != '\n' {
-                //     self.text.push(TextChar::Char(c));
-                // }
+            WindowEvent::ReceivedCharacter(c) => {
+                //Backspace, enter and other control characters come as key presses
+                if !c.is_control() {
+                    self.text.push(TextChar::Char(c));
+                }
             }
             WindowEvent::CursorMoved { position, .. } => {
                 let position = position.to_physical(self.dpi_factor);
@@ -140,5 +180,6 @@ impl Event {
 
     pub fn clear(&mut self) {
         self.resized = false;
+        self.text.clear();
     }
 }
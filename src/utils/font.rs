@@ -1,182 +1,803 @@
+use crate::utils::atlas::SkylinePacker;
+use crate::utils::layout::{measure_layout, LayoutOptions, TextLayout, TextRun, ELLIPSIS};
+use crate::utils::sdf::distance_field;
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
 use std::sync::Mutex;
 
-const ASCII_CHARS: &str = r##" !"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\]^_`abcdefghijklmnopqrstuvwxyz{|}~"##;
+/// Smallest glyph atlas page in pixels, pages are layers of the text texture.
+/// Pages grow in powers of two, when glyphs don't fit.
+pub const MIN_GLYPH_PAGE_SIZE: u32 = 256;
+
+/// Default page size limit, change it with `set_max_glyph_page_size`
+pub const MAX_GLYPH_PAGE_SIZE: u32 = 2048;
+
+/// Pixel size of distance field glyphs, other sizes are scaled from it
+pub const SDF_SIZE: i32 = 48;
+
+/// Pixels at `SDF_SIZE` around the outline, that the distance field covers
+pub const SDF_SPREAD: u32 = 6;
+
+//Full pages kept, before the whole glyph cache is cleared
+const MAX_GLYPH_PAGES: usize = 8;
+
+//Empty pixels between glyphs, so linear filtering does not bleed
+const GLYPH_PADDING: u32 = 1;
+
+//Drawn for characters missing in the font
+const REPLACEMENT_CHARS: [char; 2] = ['\u{FFFD}', '?'];
 
 lazy_static! {
-    static ref FONT_CACHE: Mutex<HashMap<String, FontPallet>> = { Mutex::new(HashMap::new()) };
+    static ref FONTS: Mutex<Vec<fontdue::Font>> = { Mutex::new(Vec::new()) };
+    static ref FONT_NAMES: Mutex<HashMap<String, FontId>> = { Mutex::new(HashMap::new()) };
+    static ref GLYPH_CACHE: Mutex<GlyphCache> = { Mutex::new(GlyphCache::default()) };
 }
 
-static mut FONT: Vec<u8> = Vec::new();
+/// Loaded font, the first loaded font has id 0
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontId(pub usize);
 
-#[derive(Clone, Debug)]
-pub struct FontBitmap {
-    pub data: Vec<u8>,
-    pub x: f32,
-    pub y: f32,
-    pub width: f32,
-    pub height: f32,
-    pub offset_x: f32,
-    pub offset_y: f32,
-    font_size: f32,
-    max_width: f32,
-    max_height: f32,
+/// Parse a truetype font once and keep it for every size
+pub fn load_font_bytes(bytes: &[u8]) -> FontId {
+    let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        .expect("failed to parse font");
+    let mut fonts = FONTS.lock().expect("lock failed");
+    fonts.push(font);
+    FontId(fonts.len() - 1)
+}
+
+/// Replace parsed font, like after the file changed. Glyph cache is cleared.
+/// Bytes, that are not a font, keep the previous font.
+pub fn reload_font_bytes(id: FontId, bytes: &[u8]) -> Result<(), String> {
+    let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        .map_err(|error| format!("Failed to parse font: {}", error))?;
+    FONTS.lock().expect("lock failed")[id.0] = font;
+    reset_glyphs();
+    Ok(())
 }
 
-#[derive(Debug, Clone)]
+/// Load font from path, same path returns the same id
+pub fn load_font(path: &str) -> FontId {
+    if let Some(id) = font_id(path) {
+        return id;
+    }
+
+    let mut f = File::open(path).expect("failed to read font");
+    let mut buffer = Vec::new();
+    f.read_to_end(&mut buffer).expect("failed to read file");
+
+    let id = load_font_bytes(&buffer);
+    register_font_name(path, id);
+    id
+}
+
+/// Make a font available by name, like "Roboto"
+pub fn register_font_name(name: &str, id: FontId) {
+    let mut names = FONT_NAMES.lock().expect("lock failed");
+    names.insert(name.to_string(), id);
+}
+
+pub fn font_id(name: &str) -> Option<FontId> {
+    let names = FONT_NAMES.lock().expect("lock failed");
+    names.get(name).cloned()
+}
+
+//Run `f` with the parsed font
+fn with_font<R, F: FnOnce(&mut fontdue::Font) -> R>(id: FontId, f: F) -> R {
+    let mut fonts = FONTS.lock().expect("lock failed");
+    let font = fonts.get_mut(id.0).expect("Failed to find font");
+    f(font)
+}
+
+#[derive(Copy, Clone, Debug)]
 pub struct UvPosition {
     pub x: [f32; 2],
     pub y: [f32; 2],
 }
 
-fn ceil(value: f64, scale: i8) -> f64 {
-    let multiplier = 10f64.powi(scale as i32) as f64;
-    (value * multiplier).ceil() / multiplier
+/// Rasterized glyph on a glyph atlas page
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    pub page: i32,
+    pub uv: UvPosition,
+    pub width: f32,
+    pub height: f32,
+    /// Distance from the pen position to the bitmap left edge
+    pub xmin: f32,
+    /// Distance from the baseline to the bitmap bottom edge, negative below the baseline
+    pub ymin: f32,
+    pub advance: f32,
+}
+
+/// Rasterized pixels waiting for upload to a glyph page
+#[derive(Clone, Debug)]
+pub struct GlyphUpload {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
 }
 
-impl FontBitmap {
-    pub fn get_uv_position(&self) -> UvPosition {
-        let padding = ceil((self.font_size / 100.0).into(), 2) as f32;
+/// Glyph atlas changes since the last `take_glyph_updates`
+#[derive(Clone, Debug)]
+pub struct GlyphUpdates {
+    /// Changes, when the cache was cleared, all glyphs have to be rebuilt
+    pub generation: u32,
+    pub page_size: u32,
+    pub page_count: usize,
+    pub uploads: Vec<GlyphUpload>,
+}
 
-        let x_start_position = ceil(((self.x - padding) / self.max_width) as f64, 3) as f32;
-        let x_end_position = ceil(
-            ((self.x + self.width + padding) as f32 / self.max_height) as f64,
-            3,
-        ) as f32;
+/// Glyph atlas usage, to check the pages against device limits
+#[derive(Clone, Debug)]
+pub struct GlyphAtlasInfo {
+    pub page_size: u32,
+    pub max_page_size: u32,
+    pub page_count: usize,
+    pub glyph_count: usize,
+    /// Highest used row of every page
+    pub used_heights: Vec<u32>,
+    /// Glyphs bigger than `max_page_size`, drawn as nothing. Use a distance field font for them.
+    pub oversized_glyphs: usize,
+}
 
-        let y_start_position = ceil(((self.y - padding) / self.max_height) as f64, 3) as f32;
+//Font, size, character and distance field flag
+type GlyphKey = (FontId, i32, char, bool);
 
-        let y_end_position = ceil(
-            ((self.y + self.height + padding) / self.max_height) as f64,
-            3,
-        ) as f32;
-        return UvPosition {
-            x: [x_start_position, x_end_position],
-            y: [y_start_position, y_end_position],
-        };
-    }
+//Glyph bitmap before it is placed on a page
+struct Rendered {
+    size: (u32, u32),
+    offset: (f32, f32),
+    advance: f32,
+    bitmap: Vec<u8>,
 }
 
-/// truetype font
-#[derive(Debug, Clone)]
-pub struct FontPallet {
-    pub max_w: i32,
-    pub max_h: i32,
-    pub characters: HashMap<char, FontBitmap>,
+struct GlyphPage {
+    pixels: Vec<u8>,
+    packer: SkylinePacker,
 }
 
-impl FontPallet {
-    /// parse a truetype file from bytes
-    pub fn create_font(font_name: &str, size: i32) -> FontPallet {
-        let font = FontPallet::cache(ASCII_CHARS, size);
-
-        let mut map = FONT_CACHE.lock().expect("lock failed");
-        map.insert(font_name.to_string(), font.clone());
-        font
-    }
-
-    /// manually cache characters
-    pub fn cache(s: &str, size: i32) -> Self {
-        let (max_texture_w, max_texture_h) = (size * size as i32, size * size as i32);
-        let mut font = unsafe { fontdue::Font::from_bytes(&FONT[..]).unwrap() };
-        let mut cur_pt: cgmath::Point2<i32> = cgmath::Point2::new(0, 0);
-        let (max_height, max_width) = FontPallet::character_offsets(size, s);
-        let mut characters: HashMap<char, FontBitmap> = HashMap::new();
-
-        for ch in s.chars() {
-            let (metrics, bitmap) = font.rasterize(ch, size as f32);
-            let (mut w, h) = (metrics.width as i32, metrics.height as i32);
-            let (mut x, mut y) = cur_pt.into();
-            let (mut offset_x, mut offset_y) = (0, 0);
-
-            //Add offsets to smaller characters
-            if metrics.width < max_width as usize {
-                offset_x = max_width - metrics.width as i32;
-            }
-            if metrics.height < max_height as usize {
-                offset_y = max_height - metrics.height as i32;
-            }
+struct GlyphCache {
+    glyphs: HashMap<GlyphKey, Glyph>,
+    pages: Vec<GlyphPage>,
+    page_size: u32,
+    max_page_size: u32,
+    uploads: Vec<GlyphUpload>,
+    generation: u32,
+    oversized: usize,
+}
 
-            //Add space char width
-            if ch == ' ' {
-                w = size / 3
-            }
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self {
+            glyphs: HashMap::new(),
+            pages: Vec::new(),
+            page_size: MIN_GLYPH_PAGE_SIZE,
+            max_page_size: MAX_GLYPH_PAGE_SIZE,
+            uploads: Vec::new(),
+            generation: 0,
+            oversized: 0,
+        }
+    }
+}
+
+impl GlyphCache {
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.pages.clear();
+        self.uploads.clear();
+        self.oversized = 0;
+        self.generation += 1;
+    }
 
-            //Put texture to new row on atlas, because current row is full
-            if x + w >= max_texture_w {
-                x = 0;
-                y += h + size as i32;
+    //Change the page size, placed glyphs are lost
+    fn resize_pages(&mut self, size: u32) {
+        if size == self.page_size {
+            return;
+        }
+        if !self.pages.is_empty() {
+            self.clear();
+        }
+        self.page_size = size;
+    }
+
+    //Smallest page size for `area` pixels of glyphs and the longest glyph side
+    fn page_size_for(&self, area: u64, longest: u32) -> u32 {
+        let mut size = self.page_size;
+        while size < self.max_page_size && ((size as u64).pow(2) < area || size < longest) {
+            size *= 2;
+        }
+        size.min(self.max_page_size)
+    }
+
+    fn add_page(&mut self) {
+        let size = self.page_size;
+        self.pages.push(GlyphPage {
+            pixels: vec![0; (size * size) as usize],
+            packer: SkylinePacker::new(size, size, GLYPH_PADDING),
+        });
+    }
+
+    //Place for a bitmap, new pages are added until MAX_GLYPH_PAGES,
+    //then the cache is cleared and pages grow until the size limit.
+    //None, when the bitmap is bigger than the size limit.
+    fn place(&mut self, width: u32, height: u32) -> Option<(usize, u32, u32)> {
+        let longest = width.max(height) + GLYPH_PADDING;
+        if longest > self.max_page_size {
+            return None;
+        }
+        if longest > self.page_size {
+            let size = self.page_size_for(0, longest);
+            self.resize_pages(size);
+        }
+
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = page.packer.pack(width, height) {
+                return Some((index, x, y));
             }
+        }
 
-            if y >= max_texture_w {
-                println!("Error, font texture too high");
+        if self.pages.len() >= MAX_GLYPH_PAGES {
+            let size = (self.page_size * 2).min(self.max_page_size);
+            self.clear();
+            self.page_size = size;
+        }
+        self.add_page();
+
+        let index = self.pages.len() - 1;
+        let (x, y) = self.pages[index]
+            .packer
+            .pack(width, height)
+            .expect("Glyph fits on an empty page");
+        Some((index, x, y))
+    }
+
+    fn insert(&mut self, key: GlyphKey, rendered: Rendered) -> Glyph {
+        let (width, height) = rendered.size;
+        let bitmap = rendered.bitmap;
+        let mut glyph = Glyph {
+            page: 0,
+            uv: UvPosition {
+                x: [0.0, 0.0],
+                y: [0.0, 0.0],
+            },
+            width: width as f32,
+            height: height as f32,
+            xmin: rendered.offset.0,
+            ymin: rendered.offset.1,
+            advance: rendered.advance,
+        };
+
+        //Whitespace has nothing to draw, glyphs too big for the pages are left empty
+        let place = if width > 0 && height > 0 {
+            self.place(width, height)
+        } else {
+            None
+        };
+        if place.is_none() && width > 0 && height > 0 {
+            glyph.width = 0.0;
+            glyph.height = 0.0;
+            self.oversized += 1;
+        }
+
+        if let Some((page, x, y)) = place {
+            let size = self.page_size as usize;
+            for row in 0..height as usize {
+                let start = (y as usize + row) * size + x as usize;
+                self.pages[page].pixels[start..start + width as usize]
+                    .copy_from_slice(&bitmap[row * width as usize..(row + 1) * width as usize]);
             }
 
-            //Add new character to bitmap
-            characters.insert(
-                ch,
-                FontBitmap {
-                    data: bitmap,
-                    y: y as f32,
-                    x: x as f32,
-                    width: w as f32,
-                    height: h as f32,
-                    offset_x: offset_x as f32,
-                    offset_y: offset_y as f32,
-                    font_size: size as f32,
-                    max_width: max_texture_w as f32,
-                    max_height: max_texture_h as f32,
-                },
-            );
-
-            //Move character forward on texture atlas
-            x += w + size as i32;
-            cur_pt = cgmath::Point2::new(x, y);
+            let texture_size = self.page_size as f32;
+            glyph.page = page as i32;
+            glyph.uv = UvPosition {
+                x: [x as f32 / texture_size, (x + width) as f32 / texture_size],
+                y: [y as f32 / texture_size, (y + height) as f32 / texture_size],
+            };
+            self.uploads.push(GlyphUpload {
+                page,
+                x,
+                y,
+                width,
+                height,
+                data: bitmap,
+            });
+        }
+
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+}
+
+//Whitespace and control characters have an empty bitmap instead of the .notdef box,
+//whitespace missing in the font is as wide as a space. None for other characters.
+fn blank_metrics(font: &fontdue::Font, size: i32, ch: char) -> Option<fontdue::Metrics> {
+    if !ch.is_whitespace() && !ch.is_control() {
+        return None;
+    }
+
+    let source = if ch.is_whitespace() && font.lookup_glyph_index(ch) != 0 {
+        ch
+    } else {
+        ' '
+    };
+    let metrics = font.metrics(source, size as f32);
+    Some(fontdue::Metrics {
+        xmin: 0,
+        ymin: 0,
+        width: 0,
+        height: 0,
+        advance_width: if ch.is_whitespace() {
+            metrics.advance_width
+        } else {
+            0.0
+        },
+        ..metrics
+    })
+}
+
+//Characters missing in the font get the replacement glyph
+fn drawn_char(font: &fontdue::Font, ch: char) -> char {
+    if font.lookup_glyph_index(ch) != 0 {
+        return ch;
+    }
+    REPLACEMENT_CHARS
+        .iter()
+        .cloned()
+        .find(|replacement| font.lookup_glyph_index(*replacement) != 0)
+        .unwrap_or(ch)
+}
+
+fn rasterize(font: FontId, size: i32, ch: char) -> (fontdue::Metrics, Vec<u8>) {
+    with_font(font, |font| match blank_metrics(font, size, ch) {
+        Some(metrics) => (metrics, Vec::new()),
+        None => font.rasterize(drawn_char(font, ch), size as f32),
+    })
+}
+
+//Metrics of the glyph `rasterize` would draw, without drawing it
+fn char_metrics(font: FontId, size: i32, ch: char) -> fontdue::Metrics {
+    with_font(font, |font| {
+        blank_metrics(font, size, ch)
+            .unwrap_or_else(|| font.metrics(drawn_char(font, ch), size as f32))
+    })
+}
+
+//Bitmap size and offset from the pen position, distance fields add the spread around the bitmap
+fn glyph_box(metrics: &fontdue::Metrics, sdf: bool) -> ((u32, u32), (f32, f32)) {
+    let (width, height) = (metrics.width as u32, metrics.height as u32);
+    let (xmin, ymin) = (metrics.xmin as f32, metrics.ymin as f32);
+    if !sdf || width == 0 || height == 0 {
+        return ((width, height), (xmin, ymin));
+    }
+
+    let spread = SDF_SPREAD as f32;
+    (
+        (width + SDF_SPREAD * 2, height + SDF_SPREAD * 2),
+        (xmin - spread, ymin - spread),
+    )
+}
+
+//Bitmap or distance field of a glyph
+fn render((font, size, ch, sdf): GlyphKey) -> Rendered {
+    let (metrics, bitmap) = rasterize(font, size, ch);
+    let (size, offset) = glyph_box(&metrics, sdf);
+    let bitmap = if sdf && size.0 > 0 && size.1 > 0 {
+        distance_field(
+            &bitmap,
+            metrics.width as u32,
+            metrics.height as u32,
+            SDF_SPREAD,
+        )
+    } else {
+        bitmap
+    };
+
+    Rendered {
+        size,
+        offset,
+        advance: metrics.advance_width,
+        bitmap,
+    }
+}
+
+//Glyph sizes from the font metrics, without a place on a glyph page
+fn unplaced_glyph((font, size, ch, sdf): GlyphKey) -> Glyph {
+    let metrics = char_metrics(font, size, ch);
+    let ((width, height), (xmin, ymin)) = glyph_box(&metrics, sdf);
+    Glyph {
+        page: 0,
+        uv: UvPosition {
+            x: [0.0, 0.0],
+            y: [0.0, 0.0],
+        },
+        width: width as f32,
+        height: height as f32,
+        xmin,
+        ymin,
+        advance: metrics.advance_width,
+    }
+}
+
+fn cached_glyph(key: GlyphKey) -> Glyph {
+    let mut cache = GLYPH_CACHE.lock().expect("lock failed");
+    if let Some(glyph) = cache.glyphs.get(&key) {
+        return *glyph;
+    }
+    cache.insert(key, render(key))
+}
+
+/// Glyph for a character, rasterized into the glyph atlas on first use.
+/// Characters missing in the font get the replacement glyph.
+pub fn glyph(font: FontId, size: i32, ch: char) -> Glyph {
+    cached_glyph((font, size, ch, false))
+}
+
+/// Distance field glyph in `SDF_SIZE`, the same glyph is scaled to every size.
+/// Bitmap includes `SDF_SPREAD` pixels around the outline.
+pub fn sdf_glyph(font: FontId, ch: char) -> Glyph {
+    cached_glyph((font, SDF_SIZE, ch, true))
+}
+
+//Render all missing glyphs first, so the page size fits the whole set.
+//Returns false, when the set does not fit in the glyph pages.
+fn cache_glyphs(keys: Vec<GlyphKey>) -> bool {
+    let mut cache = GLYPH_CACHE.lock().expect("lock failed");
+
+    //Pages can be cleared while the set is inserted, then the bigger pages get one more try
+    for _ in 0..2 {
+        let generation = cache.generation;
+        insert_glyphs(&mut cache, &keys);
+        if cache.generation == generation {
+            return true;
+        }
+    }
+    keys.iter().all(|key| cache.glyphs.contains_key(key))
+}
+
+fn insert_glyphs(cache: &mut GlyphCache, keys: &[GlyphKey]) {
+    let mut seen = HashSet::new();
+    let missing: Vec<GlyphKey> = keys
+        .iter()
+        .cloned()
+        .filter(|key| !cache.glyphs.contains_key(key) && seen.insert(*key))
+        .collect();
+    let rendered: Vec<Rendered> = missing.iter().map(|key| render(*key)).collect();
+
+    let padded = |size: u32| size + GLYPH_PADDING;
+    let area: u64 = rendered
+        .iter()
+        .filter(|glyph| glyph.size.0 > 0 && glyph.size.1 > 0)
+        .map(|glyph| padded(glyph.size.0) as u64 * padded(glyph.size.1) as u64)
+        .sum();
+    let longest = rendered
+        .iter()
+        .map(|glyph| padded(glyph.size.0.max(glyph.size.1)))
+        .max()
+        .unwrap_or(0);
+
+    let size = cache.page_size_for(area, longest);
+    if size > cache.page_size {
+        cache.resize_pages(size);
+    }
+    for (key, rendered) in missing.into_iter().zip(rendered) {
+        cache.insert(key, rendered);
+    }
+}
+
+/// Rasterize the glyphs of all runs as one set, with the spaces and ellipsis `layout` uses.
+/// Layout of the runs then only reads cached glyphs, so the glyph cache is not cleared
+/// in the middle of it. Returns false, when the set does not fit in the glyph pages.
+pub fn cache_runs(runs: &[TextRun]) -> bool {
+    let keys = runs
+        .iter()
+        .flat_map(|run| {
+            run.text
+                .chars()
+                .chain([' ', ELLIPSIS].iter().cloned())
+                .map(move |ch| run.font.key(ch))
+        })
+        .collect();
+    cache_glyphs(keys)
+}
+
+/// Largest glyph page, like the texture size limit of the device.
+/// Glyphs are cleared, when the current pages are bigger.
+pub fn set_max_glyph_page_size(size: u32) {
+    let mut cache = GLYPH_CACHE.lock().expect("lock failed");
+    cache.max_page_size = size.max(MIN_GLYPH_PAGE_SIZE);
+    if cache.page_size > cache.max_page_size {
+        let size = cache.max_page_size;
+        cache.resize_pages(size);
+    }
+}
+
+/// Current glyph pages and their usage
+pub fn glyph_atlas_info() -> GlyphAtlasInfo {
+    let cache = GLYPH_CACHE.lock().expect("lock failed");
+    GlyphAtlasInfo {
+        page_size: cache.page_size,
+        max_page_size: cache.max_page_size,
+        page_count: cache.pages.len(),
+        glyph_count: cache.glyphs.len(),
+        used_heights: cache
+            .pages
+            .iter()
+            .map(|page| page.packer.used_height())
+            .collect(),
+        oversized_glyphs: cache.oversized,
+    }
+}
+
+/// Clear all glyphs, texts have to be built again
+pub fn reset_glyphs() {
+    GLYPH_CACHE.lock().expect("lock failed").clear();
+}
+
+/// Changes, when the glyph cache is cleared
+pub fn glyph_generation() -> u32 {
+    GLYPH_CACHE.lock().expect("lock failed").generation
+}
+
+/// Take glyphs rasterized since the last call, for upload to the text texture
+pub fn take_glyph_updates() -> GlyphUpdates {
+    let mut cache = GLYPH_CACHE.lock().expect("lock failed");
+    GlyphUpdates {
+        generation: cache.generation,
+        page_size: cache.page_size,
+        page_count: cache.pages.len(),
+        uploads: std::mem::replace(&mut cache.uploads, Vec::new()),
+    }
+}
+
+/// Copy of a whole glyph page, R8 pixels
+pub fn glyph_page(page: usize) -> Vec<u8> {
+    GLYPH_CACHE.lock().expect("lock failed").pages[page]
+        .pixels
+        .clone()
+}
+
+/// Vertical font metrics in pixels, descent is below the baseline and negative
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl LineMetrics {
+    /// Distance between baselines of two rows
+    pub fn line_height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/// Size of a string laid out like `Text::build` does it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextMetrics {
+    pub width: f32,
+    pub height: f32,
+    pub line_count: usize,
+    /// Line widths without trailing spaces
+    pub line_widths: Vec<f32>,
+    /// Baselines from the top of the first line
+    pub baselines: Vec<f32>,
+}
+
+impl TextMetrics {
+    pub fn from_layout(layout: &TextLayout) -> Self {
+        TextMetrics {
+            width: layout.width,
+            height: layout.height,
+            line_count: layout.lines.len(),
+            line_widths: layout.lines.iter().map(|line| line.width).collect(),
+            baselines: layout.lines.iter().map(|line| line.baseline).collect(),
         }
+    }
+}
 
+/// Measure text without building a mesh or rasterizing glyphs, `None` width does not wrap
+pub fn measure(font: FontId, size: i32, text: &str, max_width: Option<f32>) -> TextMetrics {
+    measure_with(
+        font,
+        size,
+        text,
+        &LayoutOptions {
+            max_width,
+            ..LayoutOptions::default()
+        },
+    )
+}
+
+/// Measure with all layout options, like `Text::layout_options`.
+/// Only font metrics are read, glyphs are not rasterized into the glyph atlas.
+pub fn measure_with(font: FontId, size: i32, text: &str, options: &LayoutOptions) -> TextMetrics {
+    let runs = [TextRun {
+        text,
+        font: FontPallet::get_font(font, size),
+    }];
+    TextMetrics::from_layout(&measure_layout(&runs, options))
+}
+
+/// Font in one size, glyphs are rasterized when they are first used.
+/// Distance field fonts share glyphs between sizes, draw them with `Pipeline::draw_sdf`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FontPallet {
+    pub font: FontId,
+    pub size: i32,
+    pub sdf: bool,
+}
+
+impl FontPallet {
+    pub fn create_font(font: FontId, size: i32) -> FontPallet {
         FontPallet {
-            characters,
-            max_h: max_texture_h,
-            max_w: max_texture_w,
+            font,
+            size,
+            sdf: false,
         }
     }
 
-    //Get character offsets for correct alignment
-    pub fn character_offsets(size: i32, s: &str) -> (i32, i32) {
-        let (mut max_height, mut max_width) = (0, 0);
-        let mut font = unsafe { fontdue::Font::from_bytes(&FONT[..]).unwrap() };
+    pub fn create_sdf_font(font: FontId, size: i32) -> FontPallet {
+        FontPallet {
+            font,
+            size,
+            sdf: true,
+        }
+    }
 
-        s.chars().into_iter().for_each(|ch| {
-            let (metrics, _bitmap) = font.rasterize(ch, size as f32);
-            if metrics.height > max_height {
-                max_height = metrics.height;
-            }
-            if metrics.width > max_width {
-                max_width = metrics.width;
-            }
-        });
-        return (max_height as i32, max_width as i32);
+    fn key(&self, ch: char) -> GlyphKey {
+        if self.sdf {
+            (self.font, SDF_SIZE, ch, true)
+        } else {
+            (self.font, self.size, ch, false)
+        }
+    }
+
+    /// Rasterize characters ahead of use, like before the first frame.
+    /// Glyph pages are made big enough for the whole set, up to the size limit.
+    /// Returns false, when the set does not fit in the glyph pages.
+    pub fn cache(&self, s: &str) -> bool {
+        cache_glyphs(s.chars().map(|ch| self.key(ch)).collect())
+    }
+
+    pub fn get(&self, ch: char) -> Glyph {
+        self.scaled(cached_glyph(self.key(ch)))
     }
 
-    pub fn get(&self, ch: char) -> &FontBitmap {
-        return self.characters.get(&ch).unwrap();
+    /// Glyph size and advance from the font metrics, the glyph is not rasterized
+    /// and has no place on a glyph page. For measuring, sizes are the same as from `get`.
+    pub fn metrics(&self, ch: char) -> Glyph {
+        self.scaled(unplaced_glyph(self.key(ch)))
     }
 
-    pub fn load_font(path: &str) {
-        let mut f = File::open(path).expect("failed to read font");
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer).expect("failed to read file");
-        unsafe {
-            FONT = buffer;
+    //Distance field glyphs are scaled from `SDF_SIZE`
+    fn scaled(&self, glyph: Glyph) -> Glyph {
+        if !self.sdf {
+            return glyph;
         }
+
+        let scale = self.size as f32 / SDF_SIZE as f32;
+        Glyph {
+            width: glyph.width * scale,
+            height: glyph.height * scale,
+            xmin: glyph.xmin * scale,
+            ymin: glyph.ymin * scale,
+            advance: glyph.advance * scale,
+            ..glyph
+        }
+    }
+
+    /// Fonts without a horizontal header get 80% ascent and 20% descent
+    pub fn line_metrics(&self) -> LineMetrics {
+        let size = self.size as f32;
+        with_font(self.font, |font| font.horizontal_line_metrics(size)).map_or(
+            LineMetrics {
+                ascent: size * 0.8,
+                descent: -size * 0.2,
+                line_gap: 0.0,
+            },
+            |metrics| LineMetrics {
+                ascent: metrics.ascent,
+                descent: metrics.descent,
+                line_gap: metrics.line_gap,
+            },
+        )
+    }
+
+    /// Pen adjustment between two characters, usually negative
+    pub fn kerning(&self, left: char, right: char) -> f32 {
+        let size = self.size as f32;
+        with_font(self.font, |font| font.horizontal_kern(left, right, size)).unwrap_or(0.0)
     }
 
-    pub fn get_font(name: &str) -> FontPallet {
-        let map = FONT_CACHE.lock().expect("lock failed");
-        let font = map.get(name).expect("Failed to find font");
-        font.clone()
+    pub fn measure(&self, text: &str, max_width: Option<f32>) -> TextMetrics {
+        measure(self.font, self.size, text, max_width)
+    }
+
+    pub fn load_font(path: &str) -> FontId {
+        load_font(path)
+    }
+
+    pub fn get_font(font: FontId, size: i32) -> FontPallet {
+        FontPallet::create_font(font, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::layout::{TextAlign, WhiteSpace};
+
+    fn font() -> FontId {
+        load_font_bytes(include_bytes!("../../examples/text/assets/Roboto.ttf"))
+    }
+
+    #[test]
+    fn measure_does_not_rasterize() {
+        let font = font();
+        let metrics = measure(font, 30, "Measured, not drawn…", Some(120.0));
+        assert!(metrics.width > 0.0 && metrics.line_count > 1);
+
+        let sdf = measure_layout(
+            &[TextRun {
+                text: "Distance field",
+                font: FontPallet::create_sdf_font(font, 30),
+            }],
+            &LayoutOptions::default(),
+        );
+        assert!(sdf.width > 0.0);
+
+        let cached = GLYPH_CACHE
+            .lock()
+            .expect("lock failed")
+            .glyphs
+            .keys()
+            .any(|key| key.0 == font);
+        assert!(!cached);
+    }
+
+    #[test]
+    fn measure_with_uses_the_options() {
+        let font = font();
+        let options = LayoutOptions {
+            max_width: Some(300.0),
+            align: TextAlign::Center,
+            white_space: WhiteSpace::Pre,
+            ..LayoutOptions::default()
+        };
+        let metrics = measure_with(font, 24, "a  b", &options);
+        let left = LayoutOptions {
+            align: TextAlign::Left,
+            ..options
+        };
+
+        assert_eq!(metrics.line_count, 1);
+        assert_eq!(metrics.width, measure_with(font, 24, "a  b", &left).width);
+        assert!(metrics.width > measure(font, 24, "a  b", None).width);
+    }
+
+    #[test]
+    fn reloading_garbage_keeps_the_font() {
+        let font = font();
+        let width = measure(font, 20, "Keep me", None).width;
+
+        assert!(reload_font_bytes(font, b"not a font").is_err());
+        assert_eq!(measure(font, 20, "Keep me", None).width, width);
+    }
+
+    #[test]
+    fn oversized_glyphs_are_skipped() {
+        let font = font();
+        let big = glyph(font, 3000, 'W');
+        assert_eq!((big.width, big.height), (0.0, 0.0));
+        assert!(big.advance > 0.0);
+        assert!(glyph_atlas_info().oversized_glyphs > 0);
+
+        //Glyph cache still works
+        assert!(glyph(font, 16, 'W').width > 0.0);
     }
 }
@@ -0,0 +1,365 @@
+use crate::definitions::Vertex;
+use collision::Aabb2;
+use std::collections::HashMap;
+
+//Max vertex count, before u16 indices overflow
+const MAX_VERTICES: usize = 60000;
+const MAX_REFINE_PASSES: usize = 8;
+const EPSILON: f32 = 0.0001;
+
+#[derive(Copy, Clone, Debug)]
+pub struct ColorStop {
+    pub offset: f32,
+    pub color: [f32; 4],
+}
+
+impl ColorStop {
+    pub fn new(offset: f32, color: [f32; 4]) -> Self {
+        Self { offset, color }
+    }
+}
+
+/// Gradient paint, follows CSS `linear-gradient` and `radial-gradient` semantics.
+#[derive(Clone, Debug)]
+pub enum Gradient {
+    /// `angle` in degrees, 0 points to top and 90 to right, like in CSS.
+    Linear { angle: f32, stops: Vec<ColorStop> },
+    /// `center` is relative to the element box (0.5, 0.5 is the middle),
+    /// `radius` is in pixels, 0.0 means "farthest-corner".
+    Radial {
+        center: [f32; 2],
+        radius: f32,
+        stops: Vec<ColorStop>,
+    },
+}
+
+impl Gradient {
+    pub fn linear(angle: f32, stops: Vec<ColorStop>) -> Self {
+        Gradient::Linear { angle, stops }
+    }
+
+    pub fn radial(center: [f32; 2], radius: f32, stops: Vec<ColorStop>) -> Self {
+        Gradient::Radial {
+            center,
+            radius,
+            stops,
+        }
+    }
+
+    fn stops(&self) -> &Vec<ColorStop> {
+        match self {
+            Gradient::Linear { stops, .. } => stops,
+            Gradient::Radial { stops, .. } => stops,
+        }
+    }
+
+    //Gradient line direction and length, CSS angles are clockwise from top
+    fn linear_axis(angle: f32, bounds: &Aabb2<f32>) -> ([f32; 2], f32) {
+        let radians = angle.to_radians();
+        let (sin, cos) = radians.sin_cos();
+        let (width, height) = (bounds.max.x - bounds.min.x, bounds.max.y - bounds.min.y);
+        let length = (width * sin).abs() + (height * cos).abs();
+        ([sin, -cos], length)
+    }
+
+    fn radial_center(center: [f32; 2], bounds: &Aabb2<f32>) -> [f32; 2] {
+        [
+            bounds.min.x + (bounds.max.x - bounds.min.x) * center[0],
+            bounds.min.y + (bounds.max.y - bounds.min.y) * center[1],
+        ]
+    }
+
+    fn radial_radius(radius: f32, center: [f32; 2], bounds: &Aabb2<f32>) -> f32 {
+        if radius > 0.0 {
+            return radius;
+        }
+
+        let dx = (center[0] - bounds.min.x).abs().max((bounds.max.x - center[0]).abs());
+        let dy = (center[1] - bounds.min.y).abs().max((bounds.max.y - center[1]).abs());
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    /// Gradient position of a point, 0.0 is the first and 1.0 the last stop.
+    pub fn offset_at(&self, bounds: &Aabb2<f32>, point: [f32; 2]) -> f32 {
+        match self {
+            Gradient::Linear { angle, .. } => {
+                let (direction, length) = Gradient::linear_axis(*angle, bounds);
+                if length == 0.0 {
+                    return 0.0;
+                }
+                let center = Gradient::radial_center([0.5, 0.5], bounds);
+                let distance = (point[0] - center[0]) * direction[0]
+                    + (point[1] - center[1]) * direction[1];
+                distance / length + 0.5
+            }
+            Gradient::Radial { center, radius, .. } => {
+                let center = Gradient::radial_center(*center, bounds);
+                let radius = Gradient::radial_radius(*radius, center, bounds);
+                if radius == 0.0 {
+                    return 0.0;
+                }
+                let (dx, dy) = (point[0] - center[0], point[1] - center[1]);
+                (dx * dx + dy * dy).sqrt() / radius
+            }
+        }
+    }
+
+    /// Color at a gradient position, interpolated in premultiplied alpha like browsers do.
+    pub fn color_at_offset(&self, offset: f32) -> [f32; 4] {
+        let stops = self.stops();
+        if stops.is_empty() {
+            return [0.0, 0.0, 0.0, 0.0];
+        }
+
+        //CSS clamps stop positions, so they never go backwards
+        let mut previous = stops[0];
+        if offset <= previous.offset {
+            return previous.color;
+        }
+
+        for stop in stops.iter().skip(1) {
+            let stop_offset = stop.offset.max(previous.offset);
+            if offset <= stop_offset {
+                let range = stop_offset - previous.offset;
+                let t = if range > 0.0 {
+                    (offset - previous.offset) / range
+                } else {
+                    1.0
+                };
+                return mix_premultiplied(previous.color, stop.color, t);
+            }
+            previous = ColorStop::new(stop_offset, stop.color);
+        }
+
+        previous.color
+    }
+
+    pub fn color_at(&self, bounds: &Aabb2<f32>, point: [f32; 2]) -> [f32; 4] {
+        self.color_at_offset(self.offset_at(bounds, point))
+    }
+
+    /// Paint a tessellated mesh with the gradient.
+    /// `bounds` is the element box the gradient is relative to.
+    /// Triangles are split on every linear color stop, and refined for radial gradients,
+    /// so per vertex colors match the gradient.
+    pub fn fill(&self, bounds: Aabb2<f32>, vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>) {
+        match self {
+            Gradient::Linear { angle, stops } => {
+                let (direction, length) = Gradient::linear_axis(*angle, &bounds);
+                let center = Gradient::radial_center([0.5, 0.5], &bounds);
+                let center_distance = center[0] * direction[0] + center[1] * direction[1];
+
+                for stop in stops.iter() {
+                    let distance = center_distance + (stop.offset - 0.5) * length;
+                    split_by_line(vertices, indices, direction, distance);
+                }
+            }
+            Gradient::Radial { center, radius, .. } => {
+                let center = Gradient::radial_center(*center, &bounds);
+                let radius = Gradient::radial_radius(*radius, center, &bounds);
+                refine(vertices, indices, (radius / 8.0).max(1.0));
+            }
+        }
+
+        for vertex in vertices.iter_mut() {
+            vertex.in_color = self.color_at(&bounds, vertex.in_position);
+        }
+    }
+}
+
+fn mix_premultiplied(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    let alpha = from[3] + (to[3] - from[3]) * t;
+    if alpha <= 0.0 {
+        return [0.0, 0.0, 0.0, 0.0];
+    }
+
+    let mut color = [0.0, 0.0, 0.0, alpha];
+    for i in 0..3 {
+        let start = from[i] * from[3];
+        let end = to[i] * to[3];
+        color[i] = (start + (end - start) * t) / alpha;
+    }
+    color
+}
+
+fn lerp_vertex(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let lerp = |from: f32, to: f32| from + (to - from) * t;
+    Vertex {
+        in_position: [
+            lerp(a.in_position[0], b.in_position[0]),
+            lerp(a.in_position[1], b.in_position[1]),
+        ],
+        in_color: [
+            lerp(a.in_color[0], b.in_color[0]),
+            lerp(a.in_color[1], b.in_color[1]),
+            lerp(a.in_color[2], b.in_color[2]),
+            lerp(a.in_color[3], b.in_color[3]),
+        ],
+        tex_pos: [
+            lerp(a.tex_pos[0], b.tex_pos[0]),
+            lerp(a.tex_pos[1], b.tex_pos[1]),
+        ],
+        texture_id: a.texture_id,
+    }
+}
+
+fn edge_key(a: u16, b: u16) -> (u16, u16) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+//Split every triangle crossed by the line `dot(position, direction) == distance`.
+//Shared edges get the same new vertex, so the mesh stays without cracks.
+fn split_by_line(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    direction: [f32; 2],
+    distance: f32,
+) {
+    let side = |vertex: &Vertex| {
+        vertex.in_position[0] * direction[0] + vertex.in_position[1] * direction[1] - distance
+    };
+
+    let mut edges: HashMap<(u16, u16), u16> = HashMap::new();
+    let mut result = Vec::with_capacity(indices.len());
+
+    for triangle in indices.chunks(3) {
+        let sides: Vec<f32> = triangle.iter().map(|i| side(&vertices[*i as usize])).collect();
+        //Vertices closer than EPSILON count as lying on the line
+        let signs: Vec<i8> = sides
+            .iter()
+            .map(|d| {
+                if *d > EPSILON {
+                    1
+                } else if *d < -EPSILON {
+                    -1
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        //Triangle is fully on one side of the line
+        let crosses = signs.contains(&1) && signs.contains(&-1);
+        if !crosses || vertices.len() >= MAX_VERTICES {
+            result.extend_from_slice(triangle);
+            continue;
+        }
+
+        let mut intersect = |from: usize, to: usize| -> u16 {
+            let key = edge_key(triangle[from], triangle[to]);
+            if let Some(index) = edges.get(&key) {
+                return *index;
+            }
+            let t = sides[from] / (sides[from] - sides[to]);
+            let vertex = lerp_vertex(
+                &vertices[triangle[from] as usize],
+                &vertices[triangle[to] as usize],
+                t,
+            );
+            vertices.push(vertex);
+            let index = (vertices.len() - 1) as u16;
+            edges.insert(key, index);
+            index
+        };
+
+        //One vertex on the line, the line cuts the opposite edge
+        if let Some(on_line) = (0..3).find(|i| signs[*i] == 0) {
+            let (l, p, q) = (on_line, (on_line + 1) % 3, (on_line + 2) % 3);
+            let x = intersect(p, q);
+            result.extend_from_slice(&[triangle[l], triangle[p], x]);
+            result.extend_from_slice(&[triangle[l], x, triangle[q]]);
+            continue;
+        }
+
+        //Find the vertex, that is alone on its side
+        let lone = (0..3)
+            .find(|i| signs[*i] != signs[(i + 1) % 3] && signs[*i] != signs[(i + 2) % 3])
+            .unwrap();
+        let (l, p, q) = (lone, (lone + 1) % 3, (lone + 2) % 3);
+
+        let x = intersect(l, p);
+        let y = intersect(l, q);
+
+        result.extend_from_slice(&[triangle[l], x, y]);
+        result.extend_from_slice(&[x, triangle[p], triangle[q]]);
+        result.extend_from_slice(&[x, triangle[q], y]);
+    }
+
+    *indices = result;
+}
+
+//Split edges longer than `max_length` until the mesh is fine enough for per vertex colors
+fn refine(vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, max_length: f32) {
+    let length = |a: &Vertex, b: &Vertex| {
+        let (dx, dy) = (
+            a.in_position[0] - b.in_position[0],
+            a.in_position[1] - b.in_position[1],
+        );
+        (dx * dx + dy * dy).sqrt()
+    };
+
+    for _ in 0..MAX_REFINE_PASSES {
+        let mut midpoints: HashMap<(u16, u16), u16> = HashMap::new();
+
+        //Mark long edges first, so neighbouring triangles split the same edges
+        for triangle in indices.chunks(3) {
+            for edge in 0..3 {
+                let (a, b) = (triangle[edge], triangle[(edge + 1) % 3]);
+                let key = edge_key(a, b);
+                if midpoints.contains_key(&key)
+                    || length(&vertices[a as usize], &vertices[b as usize]) <= max_length
+                    || vertices.len() >= MAX_VERTICES
+                {
+                    continue;
+                }
+                let vertex = lerp_vertex(&vertices[a as usize], &vertices[b as usize], 0.5);
+                vertices.push(vertex);
+                midpoints.insert(key, (vertices.len() - 1) as u16);
+            }
+        }
+
+        if midpoints.is_empty() {
+            return;
+        }
+
+        let mut result = Vec::with_capacity(indices.len() * 4);
+        for triangle in indices.chunks(3) {
+            let mids: Vec<Option<u16>> = (0..3)
+                .map(|edge| {
+                    midpoints
+                        .get(&edge_key(triangle[edge], triangle[(edge + 1) % 3]))
+                        .cloned()
+                })
+                .collect();
+
+            match mids.iter().filter(|mid| mid.is_some()).count() {
+                0 => result.extend_from_slice(triangle),
+                1 => {
+                    //Rotate, so the split edge is v0 -> v1
+                    let r = (0..3).find(|edge| mids[*edge].is_some()).unwrap();
+                    let (a, b, c) = (triangle[r], triangle[(r + 1) % 3], triangle[(r + 2) % 3]);
+                    let ab = mids[r].unwrap();
+                    result.extend_from_slice(&[a, ab, c, ab, b, c]);
+                }
+                2 => {
+                    //Rotate, so the edge without split is v2 -> v0
+                    let r = (0..3).find(|edge| mids[(edge + 2) % 3].is_none()).unwrap();
+                    let (a, b, c) = (triangle[r], triangle[(r + 1) % 3], triangle[(r + 2) % 3]);
+                    let (ab, bc) = (mids[r].unwrap(), mids[(r + 1) % 3].unwrap());
+                    result.extend_from_slice(&[a, ab, bc, a, bc, c, ab, b, bc]);
+                }
+                _ => {
+                    let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+                    let (ab, bc, ca) = (mids[0].unwrap(), mids[1].unwrap(), mids[2].unwrap());
+                    result.extend_from_slice(&[a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+                }
+            }
+        }
+
+        *indices = result;
+    }
+}
@@ -0,0 +1,649 @@
+use crate::utils::font::{FontPallet, Glyph, LineMetrics};
+use crate::utils::Style;
+use std::collections::HashMap;
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+
+//Tab width in spaces, for `WhiteSpace::Pre`
+const TAB_SPACES: f32 = 4.0;
+
+//Drawn at the end of cut lines, with `Overflow::Ellipsis`
+pub(crate) const ELLIPSIS: char = '…';
+
+//Glyph of a character in a font, rasterized or from metrics only
+type GlyphSource = fn(&FontPallet, char) -> Glyph;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WhiteSpace {
+    /// Spaces are collapsed, lines wrap at word boundaries
+    Normal,
+    /// Spaces and tabs are kept, lines never wrap
+    Pre,
+    /// Spaces are collapsed, lines never wrap
+    NoWrap,
+}
+
+/// Horizontal line position in the box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    /// Spaces of wrapped lines are stretched to the box width, last paragraph line is left
+    Justify,
+}
+
+/// Vertical text position in the box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+    /// First line baseline is on the box top edge
+    Baseline,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overflow {
+    /// Lines outside the box are drawn
+    Visible,
+    /// Lines and glyphs outside the box are dropped
+    Clip,
+    /// Like clip, last visible line ends with "…"
+    Ellipsis,
+}
+
+/// Layout limits, `\n` always starts a new line.
+/// Alignment uses `max_width` and `max_height` as the box size, without them
+/// text is aligned around the origin, like centered on it.
+#[derive(Copy, Clone, Debug)]
+pub struct LayoutOptions {
+    pub max_width: Option<f32>,
+    pub max_height: Option<f32>,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    pub max_lines: Option<usize>,
+    pub white_space: WhiteSpace,
+    pub overflow: Overflow,
+    /// Multiplier of the font line height
+    pub line_height: f32,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            max_lines: None,
+            white_space: WhiteSpace::Normal,
+            overflow: Overflow::Visible,
+            line_height: 1.0,
+        }
+    }
+}
+
+impl LayoutOptions {
+    /// Box limits from style, zero width or height is unlimited
+    pub fn from_style(style: &Style) -> Self {
+        let limit = |size: f32| if size > 0.0 { Some(size) } else { None };
+        Self {
+            max_width: limit(style.width),
+            max_height: limit(style.height),
+            ..LayoutOptions::default()
+        }
+    }
+}
+
+/// Text in one font
+#[derive(Copy, Clone, Debug)]
+pub struct TextRun<'a> {
+    pub text: &'a str,
+    pub font: FontPallet,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PositionedGlyph {
+    pub ch: char,
+    pub glyph: Glyph,
+    /// Pen position, relative to the layout top left corner
+    pub x: f32,
+    pub baseline: f32,
+    pub run: usize,
+    /// Byte offset in the run text. The ellipsis has the offset of the first removed
+    /// character, or of the last character, when nothing was removed.
+    pub byte: usize,
+    pub line: usize,
+}
+
+impl PositionedGlyph {
+    /// Bitmap top left corner
+    pub fn top_left(&self) -> (f32, f32) {
+        (
+            self.x + self.glyph.xmin,
+            self.baseline - self.glyph.height - self.glyph.ymin,
+        )
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.glyph.width > 0.0 && self.glyph.height > 0.0
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct LayoutLine {
+    /// Glyph range in `TextLayout::glyphs`
+    pub start: usize,
+    pub end: usize,
+    /// Left edge, after alignment
+    pub x: f32,
+    /// Width without trailing spaces
+    pub width: f32,
+    pub top: f32,
+    pub baseline: f32,
+    pub height: f32,
+    /// Line ends with `\n` or the text end, not wrapped
+    pub hard_break: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    pub lines: Vec<LayoutLine>,
+    /// Bounding box of all lines
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Text did not fit and was clipped or truncated
+    pub truncated: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Item {
+    ch: char,
+    position: usize,
+    run: usize,
+    byte: usize,
+}
+
+//Characters, that always end the line, like `\n` or the line separator U+2028
+fn is_line_break(ch: char) -> bool {
+    match ch {
+        '\n' | '\u{000B}' | '\u{000C}' | '\u{0085}' | '\u{2028}' | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+//Characters after white space processing, with their position in the joined text
+fn collect_items(runs: &[TextRun], white_space: WhiteSpace) -> (String, Vec<Item>) {
+    let mut text = String::new();
+    let mut items: Vec<Item> = Vec::new();
+
+    for (run, text_run) in runs.iter().enumerate() {
+        for (byte, ch) in text_run.text.char_indices() {
+            if ch == '\r' {
+                continue;
+            }
+            let ch = if white_space != WhiteSpace::Pre && !is_line_break(ch) && ch.is_whitespace() {
+                //Collapse spaces, also at the start of a line
+                match items.last() {
+                    Some(last) if last.ch != ' ' && !is_line_break(last.ch) => ' ',
+                    _ => continue,
+                }
+            } else {
+                ch
+            };
+
+            items.push(Item {
+                ch,
+                position: text.len(),
+                run,
+                byte,
+            });
+            text.push(ch);
+        }
+    }
+
+    (text, items)
+}
+
+/// Lay out runs of text, wrapping and truncating by `options`
+pub fn layout(runs: &[TextRun], options: &LayoutOptions) -> TextLayout {
+    layout_from(runs, options, FontPallet::get)
+}
+
+/// Same layout as `layout`, from font metrics only. Glyphs are not rasterized into
+/// the glyph atlas and have no place on a glyph page, for measuring.
+pub fn measure_layout(runs: &[TextRun], options: &LayoutOptions) -> TextLayout {
+    layout_from(runs, options, FontPallet::metrics)
+}
+
+fn layout_from(runs: &[TextRun], options: &LayoutOptions, glyph: GlyphSource) -> TextLayout {
+    let (text, items) = collect_items(runs, options.white_space);
+    let breaks: HashMap<usize, BreakOpportunity> = linebreaks(&text).collect();
+    let metrics: Vec<LineMetrics> = runs.iter().map(|run| run.font.line_metrics()).collect();
+
+    let glyphs: Vec<Glyph> = items
+        .iter()
+        .map(|item| glyph(&runs[item.run].font, item.ch))
+        .collect();
+
+    //Pen movement of every item, kerning is added when the previous item is on the same line
+    let advance = |index: usize| -> f32 {
+        let item = items[index];
+        if item.ch == '\t' {
+            return glyph(&runs[item.run].font, ' ').advance * TAB_SPACES;
+        }
+        glyphs[index].advance
+    };
+    let kerning = |index: usize| -> f32 {
+        let (previous, item) = (items[index - 1], items[index]);
+        if previous.run == item.run {
+            runs[item.run].font.kerning(previous.ch, item.ch)
+        } else {
+            0.0
+        }
+    };
+
+    //Split to lines, as item ranges
+    let max_width = options.max_width.unwrap_or(std::f32::INFINITY);
+    let wrap = options.white_space == WhiteSpace::Normal && options.max_width.is_some();
+    let mut ranges: Vec<(usize, usize, bool)> = Vec::new();
+    let (mut start, mut index, mut x) = (0, 0, 0.0);
+    let mut last_break: Option<usize> = None;
+
+    while index < items.len() {
+        let item = items[index];
+        if is_line_break(item.ch) {
+            ranges.push((start, index, true));
+            start = index + 1;
+            index = start;
+            x = 0.0;
+            last_break = None;
+            continue;
+        }
+
+        if index > start {
+            match breaks.get(&item.position) {
+                Some(BreakOpportunity::Mandatory) => {
+                    ranges.push((start, index, true));
+                    start = index;
+                    x = 0.0;
+                    last_break = None;
+                    continue;
+                }
+                Some(BreakOpportunity::Allowed) => last_break = Some(index),
+                None => {}
+            }
+        }
+
+        let kern = if index > start { kerning(index) } else { 0.0 };
+        let right = x + kern + advance(index);
+        if wrap && index > start && !item.ch.is_whitespace() && right > max_width {
+            //Break at the last word boundary, long words are broken anywhere
+            let end = match last_break {
+                Some(position) if position > start => position,
+                _ => index,
+            };
+            ranges.push((start, end, false));
+
+            //Collapsed space at the wrap point is not drawn on the next line
+            start = end;
+            while start < items.len() && items[start].ch == ' ' {
+                start += 1;
+            }
+            index = start;
+            x = 0.0;
+            last_break = None;
+            continue;
+        }
+
+        x = right;
+        index += 1;
+    }
+    ranges.push((start, items.len(), true));
+
+    //Place glyphs on lines
+    let mut result = TextLayout::default();
+    let mut top = 0.0;
+    for (line, (start, end, hard_break)) in ranges.iter().enumerate() {
+        let run = items
+            .get(*start)
+            .or_else(|| items.get(start.saturating_sub(1)))
+            .map_or(0, |item| item.run);
+        let (mut ascent, mut descent, mut gap) =
+            metrics.get(run).map_or((0.0, 0.0, 0.0), |metrics| {
+                (metrics.ascent, metrics.descent, metrics.line_gap)
+            });
+        for item in items[*start..*end].iter() {
+            let metrics = metrics[item.run];
+            ascent = f32::max(ascent, metrics.ascent);
+            descent = f32::min(descent, metrics.descent);
+            gap = f32::max(gap, metrics.line_gap);
+        }
+        //Extra line height is split above and below the glyphs
+        let height = (ascent - descent + gap) * options.line_height;
+        let baseline = top + ascent + (height - (ascent - descent + gap)) / 2.0;
+
+        let first = result.glyphs.len();
+        let (mut x, mut width) = (0.0, 0.0);
+        for index in *start..*end {
+            if index > *start {
+                x += kerning(index);
+            }
+            let item = items[index];
+            result.glyphs.push(PositionedGlyph {
+                ch: item.ch,
+                glyph: glyphs[index],
+                x,
+                baseline,
+                run: item.run,
+                byte: item.byte,
+                line,
+            });
+            x += advance(index);
+            if !item.ch.is_whitespace() {
+                width = x;
+            }
+        }
+
+        result.lines.push(LayoutLine {
+            start: first,
+            end: result.glyphs.len(),
+            x: 0.0,
+            width,
+            top,
+            baseline,
+            height,
+            hard_break: *hard_break,
+        });
+        top += height;
+    }
+
+    if options.overflow != Overflow::Visible {
+        truncate(&mut result, runs, options, glyph);
+    }
+    align(&mut result, options);
+
+    result.x = result
+        .lines
+        .iter()
+        .map(|line| line.x)
+        .fold(None, |x: Option<f32>, line_x| {
+            Some(x.map_or(line_x, |x| x.min(line_x)))
+        })
+        .unwrap_or(0.0);
+    result.width = result.lines.iter().fold(0.0, |width, line| {
+        f32::max(width, line.x + line.width - result.x)
+    });
+    result.y = result.lines.first().map_or(0.0, |line| line.top);
+    result.height = result
+        .lines
+        .last()
+        .map_or(0.0, |line| line.top + line.height - result.y);
+    result
+}
+
+//Move lines by the alignment in the box
+fn align(result: &mut TextLayout, options: &LayoutOptions) {
+    let box_width = options.max_width.unwrap_or(0.0);
+    for line in result.lines.iter_mut() {
+        let extra = box_width - line.width;
+        line.x = match options.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => extra / 2.0,
+            TextAlign::Right => extra,
+            TextAlign::Justify => {
+                //Stretch spaces between words, trailing spaces stay as they are
+                let glyphs = &mut result.glyphs[line.start..line.end];
+                let last_word = glyphs.iter().rposition(|glyph| !glyph.ch.is_whitespace());
+                let spaces = match last_word {
+                    Some(last) => glyphs[..last]
+                        .iter()
+                        .filter(|glyph| glyph.ch == ' ')
+                        .count(),
+                    None => 0,
+                };
+
+                if !line.hard_break && spaces > 0 && extra > 0.0 && options.max_width.is_some() {
+                    let gap = extra / spaces as f32;
+                    let mut shift = 0.0;
+                    for glyph in glyphs.iter_mut() {
+                        glyph.x += shift;
+                        if glyph.ch == ' ' {
+                            shift += gap;
+                        }
+                    }
+                    line.width = box_width;
+                }
+                0.0
+            }
+        };
+
+        for glyph in result.glyphs[line.start..line.end].iter_mut() {
+            glyph.x += line.x;
+        }
+    }
+
+    let box_height = options.max_height.unwrap_or(0.0);
+    let height = result
+        .lines
+        .last()
+        .map_or(0.0, |line| line.top + line.height);
+    let shift = match options.vertical_align {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Middle => (box_height - height) / 2.0,
+        VerticalAlign::Bottom => box_height - height,
+        VerticalAlign::Baseline => -result.lines.first().map_or(0.0, |line| line.baseline),
+    };
+    for line in result.lines.iter_mut() {
+        line.top += shift;
+        line.baseline += shift;
+    }
+    for glyph in result.glyphs.iter_mut() {
+        glyph.baseline += shift;
+    }
+}
+
+//Drop lines and glyphs outside the box, add the ellipsis
+fn truncate(
+    result: &mut TextLayout,
+    runs: &[TextRun],
+    options: &LayoutOptions,
+    glyph: GlyphSource,
+) {
+    let mut visible = result.lines.len();
+    if let Some(max_lines) = options.max_lines {
+        visible = visible.min(max_lines);
+    }
+    if let Some(max_height) = options.max_height {
+        let fitting = result
+            .lines
+            .iter()
+            .take_while(|line| line.top + line.height <= max_height)
+            .count();
+        visible = visible.min(fitting.max(1));
+    }
+
+    let cut_lines = visible < result.lines.len();
+    if cut_lines {
+        result.truncated = true;
+        result.lines.truncate(visible);
+        let end = result.lines.last().map_or(0, |line| line.end);
+        result.glyphs.truncate(end);
+    }
+
+    let max_width = options.max_width.unwrap_or(std::f32::INFINITY);
+    let last = result.lines.len().saturating_sub(1);
+    for line_index in 0..result.lines.len() {
+        let line = result.lines[line_index];
+        let overflows = line.width > max_width;
+        if !overflows && !(cut_lines && line_index == last) {
+            continue;
+        }
+        result.truncated = true;
+
+        let mut end = line.end;
+        if options.overflow == Overflow::Ellipsis {
+            let run = match result.glyphs[line.start..end].last() {
+                Some(glyph) => glyph.run,
+                None => continue,
+            };
+            let ellipsis = glyph(&runs[run].font, ELLIPSIS);
+
+            //Remove characters until the ellipsis fits
+            let cut_byte = result.glyphs[end - 1].byte;
+            let mut byte = cut_byte;
+            while end > line.start {
+                let glyph = result.glyphs[end - 1];
+                let right = glyph.x + glyph.glyph.advance;
+                if !glyph.ch.is_whitespace() && right + ellipsis.advance <= max_width {
+                    break;
+                }
+                byte = glyph.byte;
+                end -= 1;
+            }
+
+            let x = result.glyphs[line.start..end]
+                .last()
+                .map_or(0.0, |glyph| glyph.x + glyph.glyph.advance);
+            let glyph = PositionedGlyph {
+                ch: ELLIPSIS,
+                glyph: ellipsis,
+                x,
+                baseline: line.baseline,
+                run,
+                byte: if end < line.end { byte } else { cut_byte },
+                line: line_index,
+            };
+
+            //Ellipsis replaces the removed glyphs, following lines move back
+            result.glyphs.drain(end..line.end);
+            result.glyphs.insert(end, glyph);
+            end += 1;
+        } else {
+            //Clip glyphs, that don't fit fully
+            while end > line.start {
+                let glyph = result.glyphs[end - 1];
+                if glyph.x + glyph.glyph.xmin + glyph.glyph.width <= max_width {
+                    break;
+                }
+                end -= 1;
+            }
+            result.glyphs.drain(end..line.end);
+        }
+
+        let removed = line.end as isize - end as isize;
+        result.lines[line_index].end = end;
+        result.lines[line_index].width = result.glyphs[line.start..end]
+            .iter()
+            .filter(|glyph| !glyph.ch.is_whitespace())
+            .last()
+            .map_or(0.0, |glyph| glyph.x + glyph.glyph.advance);
+        for next in result.lines[line_index + 1..].iter_mut() {
+            next.start = (next.start as isize - removed) as usize;
+            next.end = (next.end as isize - removed) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::font::load_font_bytes;
+
+    fn font() -> FontPallet {
+        let font = load_font_bytes(include_bytes!("../../examples/text/assets/Roboto.ttf"));
+        FontPallet::create_font(font, 16)
+    }
+
+    fn lay_out(font: FontPallet, text: &str, options: LayoutOptions) -> TextLayout {
+        layout(&[TextRun { text, font }], &options)
+    }
+
+    fn width(font: FontPallet, text: &str) -> f32 {
+        lay_out(font, text, LayoutOptions::default()).width
+    }
+
+    //Characters of every line
+    fn lines(layout: &TextLayout) -> Vec<String> {
+        layout
+            .lines
+            .iter()
+            .map(|line| {
+                layout.glyphs[line.start..line.end]
+                    .iter()
+                    .map(|glyph| glyph.ch)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mandatory_break() {
+        let font = font();
+        let layout = lay_out(font, "one two\nthree\n\nfour", LayoutOptions::default());
+
+        assert_eq!(lines(&layout), vec!["one two", "three", "", "four"]);
+        assert!(layout.lines.iter().all(|line| line.hard_break));
+
+        //Line separator is kept, not collapsed to a space
+        let layout = lay_out(
+            font,
+            "one\u{2028}two  \u{2029} three",
+            LayoutOptions::default(),
+        );
+        assert_eq!(lines(&layout), vec!["one", "two ", "three"]);
+    }
+
+    #[test]
+    fn wraps_at_the_last_break() {
+        let font = font();
+        let options = LayoutOptions {
+            max_width: Some(width(font, "one two") + 1.0),
+            ..LayoutOptions::default()
+        };
+        let layout = lay_out(font, "one two three", options);
+
+        assert_eq!(lines(&layout), vec!["one two ", "three"]);
+        assert!(!layout.lines[0].hard_break);
+        assert!(layout.lines[1].hard_break);
+    }
+
+    #[test]
+    fn splits_long_words() {
+        let font = font();
+        let max_width = width(font, "abcd") + 1.0;
+        let options = LayoutOptions {
+            max_width: Some(max_width),
+            ..LayoutOptions::default()
+        };
+        let layout = lay_out(font, "abcdefghij", options);
+
+        let lines = lines(&layout);
+        assert!(lines.len() > 1);
+        assert_eq!(lines[0], "abcd");
+        assert_eq!(lines.concat(), "abcdefghij");
+        assert!(layout.lines.iter().all(|line| line.width <= max_width));
+    }
+
+    #[test]
+    fn pre_whitespace_has_no_bitmap() {
+        let font = font();
+        let options = LayoutOptions {
+            white_space: WhiteSpace::Pre,
+            ..LayoutOptions::default()
+        };
+        let layout = lay_out(font, "a\t\u{a0}\u{3000}\u{7}b", options);
+
+        let visible: String = layout
+            .glyphs
+            .iter()
+            .filter(|glyph| glyph.is_visible())
+            .map(|glyph| glyph.ch)
+            .collect();
+        assert_eq!(visible, "ab");
+        assert_eq!(layout.glyphs[3].glyph.advance, font.get(' ').advance);
+    }
+}
@@ -1,12 +1,37 @@
+pub mod animation;
+pub mod assets;
+pub mod atlas;
 pub mod batch;
+pub mod collider;
 pub mod font;
+pub mod gradient;
+pub mod layout;
+pub mod sdf;
+pub mod sequence;
+pub mod shadow;
+pub mod spatial;
+pub mod sprite;
 pub mod style;
+pub mod texture;
+pub mod transform;
 pub mod events;
 
-pub use events::Event;
-pub use batch::Batch;
-pub use font::FontPallet;
+pub use events::{Event, TextChar};
+pub use animation::{AnimationEvent, AnimationFrame, PlayMode, SpriteAnimation};
+pub use assets::{AssetManager, FontAsset, Handle, ReloadError, ShaderAsset};
+pub use atlas::{Atlas, AtlasBuilder, AtlasError};
+pub use batch::{Batch, CullStats};
+pub use collider::Collider;
+pub use font::{measure, FontId, FontPallet, TextMetrics};
+pub use gradient::{ColorStop, Gradient};
+pub use layout::{LayoutOptions, Overflow, TextAlign, TextLayout, VerticalAlign, WhiteSpace};
+pub use sequence::ImageSequence;
+pub use shadow::BoxShadow;
+pub use spatial::SpatialIndex;
+pub use sprite::{Frame, SpriteSheet, UvRect};
 pub use style::Style;
+pub use texture::{AddressMode, FilterMode, TextureOptions};
+pub use transform::Transform;
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -17,6 +42,7 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 );
 
 #[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
 pub enum ShaderStage {
     Vertex,
     Fragment,
@@ -25,11 +51,17 @@ pub enum ShaderStage {
 
 #[allow(dead_code)]
 pub fn load_glsl(code: &str, stage: ShaderStage) -> Vec<u32> {
+    try_load_glsl(code, stage).unwrap()
+}
+
+/// Compile GLSL, returns the compiler message on failure
+pub fn try_load_glsl(code: &str, stage: ShaderStage) -> Result<Vec<u32>, String> {
     let ty = match stage {
         ShaderStage::Vertex => glsl_to_spirv::ShaderType::Vertex,
         ShaderStage::Fragment => glsl_to_spirv::ShaderType::Fragment,
         ShaderStage::Compute => glsl_to_spirv::ShaderType::Compute,
     };
 
-    wgpu::read_spirv(glsl_to_spirv::compile(&code, ty).unwrap()).unwrap()
+    let spirv = glsl_to_spirv::compile(&code, ty).map_err(|error| error.to_string())?;
+    wgpu::read_spirv(spirv).map_err(|error| error.to_string())
 }
@@ -0,0 +1,39 @@
+//Coverage above this is inside the glyph
+const INSIDE: u8 = 128;
+
+/// Signed distance field of a coverage bitmap, padded by `spread` pixels on every side.
+/// 128 is the outline, values above are inside and `spread` pixels away saturate.
+pub fn distance_field(coverage: &[u8], width: u32, height: u32, spread: u32) -> Vec<u8> {
+    let (width, height, spread) = (width as i32, height as i32, spread as i32);
+    let (field_width, field_height) = (width + spread * 2, height + spread * 2);
+
+    let inside = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && x < width && y < height && coverage[(y * width + x) as usize] >= INSIDE
+    };
+
+    let mut field = Vec::with_capacity((field_width * field_height) as usize);
+    for field_y in 0..field_height {
+        for field_x in 0..field_width {
+            let (x, y) = (field_x - spread, field_y - spread);
+            let is_inside = inside(x, y);
+
+            //Nearest pixel on the other side of the outline
+            let mut nearest = ((spread + 1) * (spread + 1)) as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    let distance = (dx * dx + dy * dy) as f32;
+                    if distance < nearest && inside(x + dx, y + dy) != is_inside {
+                        nearest = distance;
+                    }
+                }
+            }
+
+            //Outline is between pixel centers
+            let distance = (nearest.sqrt() - 0.5).max(0.0).min(spread as f32);
+            let signed = if is_inside { distance } else { -distance };
+            let value = 0.5 + signed / (spread as f32 * 2.0);
+            field.push((value * 255.0).round().max(0.0).min(255.0) as u8);
+        }
+    }
+    field
+}
@@ -0,0 +1,179 @@
+use crate::utils::animation::{PlayMode, SpriteAnimation};
+use image::{ImageError, ImageResult, Rgba, RgbaImage};
+
+//Browsers show GIF frames with tiny delays for 100ms, most GIFs expect that
+const MIN_GIF_DELAY: f32 = 0.02;
+const DEFAULT_GIF_DELAY: f32 = 0.1;
+
+/// Most images `ImageSequence::open_numbered` probes for
+pub const MAX_SEQUENCE_FRAMES: usize = 10_000;
+
+//Canvas pixels covered by a frame
+fn frame_area(
+    frame: &gif::Frame,
+    width: u32,
+    height: u32,
+) -> impl Iterator<Item = (u32, u32)> + Clone {
+    let (left, top) = (u32::from(frame.left), u32::from(frame.top));
+    let right = (left + u32::from(frame.width)).min(width);
+    let bottom = (top + u32::from(frame.height)).min(height);
+    (top..bottom).flat_map(move |y| (left..right).map(move |x| (x, y)))
+}
+
+/// Animation frames of the same size with their delays in seconds,
+/// ready for `Pipeline::create_texture`, one layer per frame.
+#[derive(Clone, Debug)]
+pub struct ImageSequence {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<RgbaImage>,
+    pub delays: Vec<f32>,
+}
+
+impl ImageSequence {
+    /// Decode animated GIF, frames are drawn over the previous ones and disposed
+    /// as the file asks, "restore to background" clears the frame area to transparent.
+    pub fn from_gif_bytes(bytes: &[u8]) -> ImageResult<Self> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options
+            .read_info(std::io::Cursor::new(bytes))
+            .map_err(|error| ImageError::FormatError(error.to_string()))?;
+
+        let (width, height) = (u32::from(decoder.width()), u32::from(decoder.height()));
+        let mut canvas = RgbaImage::new(width, height);
+        let mut images = Vec::new();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|error| ImageError::FormatError(error.to_string()))?
+        {
+            let delay = f32::from(frame.delay) / 100.0;
+            delays.push(if delay < MIN_GIF_DELAY {
+                DEFAULT_GIF_DELAY
+            } else {
+                delay
+            });
+
+            let previous = match frame.dispose {
+                gif::DisposalMethod::Previous => Some(canvas.clone()),
+                _ => None,
+            };
+
+            //Transparent pixels keep the canvas, frames can reach outside of it
+            let (left, top) = (u32::from(frame.left), u32::from(frame.top));
+            let area = frame_area(&frame, width, height);
+            for (x, y) in area.clone() {
+                let start = (((y - top) * u32::from(frame.width) + x - left) * 4) as usize;
+                let pixel = &frame.buffer[start..start + 4];
+                if pixel[3] > 0 {
+                    canvas.put_pixel(x, y, Rgba([pixel[0], pixel[1], pixel[2], pixel[3]]));
+                }
+            }
+            images.push(canvas.clone());
+
+            match frame.dispose {
+                gif::DisposalMethod::Background => {
+                    for (x, y) in area {
+                        canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+                    }
+                }
+                gif::DisposalMethod::Previous => canvas = previous.unwrap(),
+                _ => {}
+            }
+        }
+
+        if images.is_empty() || width == 0 || height == 0 {
+            return Err(ImageError::FormatError("GIF has no frames".to_string()));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            frames: images,
+            delays,
+        })
+    }
+
+    /// Animated GIF from path, relative to the current directory
+    pub fn open_gif(path: &str) -> ImageResult<Self> {
+        let absolute_path = std::env::current_dir().expect("Bad image path");
+        let bytes = std::fs::read(absolute_path.join(path))?;
+        ImageSequence::from_gif_bytes(&bytes)
+    }
+
+    /// Numbered images, one run of `#` in the pattern is replaced by the zero padded frame number,
+    /// like "walk_###.png" for walk_001.png, walk_002.png... Loads until a number is missing,
+    /// at most `MAX_SEQUENCE_FRAMES` images.
+    pub fn open_numbered(pattern: &str, start: usize, duration: f32) -> ImageResult<Self> {
+        let digits = pattern.matches('#').count();
+        let placeholder = "#".repeat(digits);
+        if digits == 0 || !pattern.contains(&placeholder) {
+            return Err(ImageError::FormatError(format!(
+                "Sequence pattern {} needs one run of # for the frame number",
+                pattern
+            )));
+        }
+
+        let absolute_path = std::env::current_dir().expect("Bad image path");
+        let mut frames: Vec<RgbaImage> = Vec::new();
+        for number in (start..).take(MAX_SEQUENCE_FRAMES) {
+            let name = pattern.replacen(
+                &placeholder,
+                &format!("{:0width$}", number, width = digits),
+                1,
+            );
+            let path = absolute_path.join(name);
+            if !path.exists() {
+                break;
+            }
+
+            let frame = image::open(path)?.to_rgba();
+            if let Some(first) = frames.first() {
+                if first.dimensions() != frame.dimensions() {
+                    return Err(ImageError::DimensionError);
+                }
+            }
+            frames.push(frame);
+        }
+
+        let (width, height) = match frames.first() {
+            Some(frame) if frame.width() > 0 && frame.height() > 0 => frame.dimensions(),
+            _ => {
+                return Err(ImageError::FormatError(format!(
+                    "No images found for {}",
+                    pattern
+                )))
+            }
+        };
+        let delays = vec![duration; frames.len()];
+
+        Ok(Self {
+            width,
+            height,
+            frames,
+            delays,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// RGBA layers for `Pipeline::create_texture`
+    pub fn faces(&self) -> Vec<Vec<u8>> {
+        self.frames
+            .iter()
+            .map(|frame| frame.clone().into_raw())
+            .collect()
+    }
+
+    /// Playback with the sequence delays, `first_layer` is the texture layer of the first frame
+    pub fn animation(&self, first_layer: i32, mode: PlayMode) -> SpriteAnimation {
+        SpriteAnimation::from_delays(first_layer, &self.delays, mode)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::definitions::{Mesh, Vertex};
+
+//Rings used to fake the gaussian falloff
+const BLUR_STEPS: usize = 8;
+const CORNER_SEGMENTS: usize = 6;
+
+/// CSS like `box-shadow`, drawn behind the element and never changes its layout size.
+#[derive(Copy, Clone, Debug)]
+pub struct BoxShadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub blur: f32,
+    pub spread: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for BoxShadow {
+    fn default() -> Self {
+        Self {
+            offset_x: 0.0,
+            offset_y: 0.0,
+            blur: 0.0,
+            spread: 0.0,
+            color: [0.0, 0.0, 0.0, 0.5],
+        }
+    }
+}
+
+//Abramowitz and Stegun approximation, good enough for alpha values
+fn erf(x: f32) -> f32 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let y = 1.0
+        - (((((1.061_405_4 * t - 1.453_152_1) * t) + 1.421_413_7) * t - 0.284_496_74) * t
+            + 0.254_829_6)
+            * t
+            * (-x * x).exp();
+    sign * y
+}
+
+impl BoxShadow {
+    //Shadow coverage at signed distance from the shadow edge, negative is inside
+    fn alpha(&self, distance: f32) -> f32 {
+        let sigma = self.blur / 2.0;
+        let coverage = 0.5 * (1.0 - erf(distance / (sigma * std::f32::consts::SQRT_2)));
+        self.color[3] * coverage
+    }
+
+    //Rounded rectangle outline, expanded by `distance`
+    fn contour(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        radius: f32,
+        distance: f32,
+    ) -> Vec<[f32; 2]> {
+        let half_w = (width / 2.0 + distance).max(0.0);
+        let half_h = (height / 2.0 + distance).max(0.0);
+        let (center_x, center_y) = (x + width / 2.0, y + height / 2.0);
+        let radius = (radius + distance).max(0.0).min(half_w).min(half_h);
+
+        //Corner circle centers, clockwise from top left
+        let corners = [
+            (center_x - half_w + radius, center_y - half_h + radius, 180.0f32),
+            (center_x + half_w - radius, center_y - half_h + radius, 270.0),
+            (center_x + half_w - radius, center_y + half_h - radius, 0.0),
+            (center_x - half_w + radius, center_y + half_h - radius, 90.0),
+        ];
+
+        let mut points = Vec::with_capacity(4 * (CORNER_SEGMENTS + 1));
+        for (corner_x, corner_y, start) in corners.iter() {
+            for segment in 0..=CORNER_SEGMENTS {
+                let angle = (start + 90.0 * segment as f32 / CORNER_SEGMENTS as f32).to_radians();
+                points.push([
+                    corner_x + radius * angle.cos(),
+                    corner_y + radius * angle.sin(),
+                ]);
+            }
+        }
+        points
+    }
+
+    fn vertex(&self, position: [f32; 2], alpha: f32) -> Vertex {
+        Vertex {
+            in_position: position,
+            in_color: [self.color[0], self.color[1], self.color[2], alpha],
+            tex_pos: [0.0, 0.0],
+            texture_id: -1,
+        }
+    }
+
+    /// Build shadow mesh for an element box, `radius` is the element corner radius.
+    pub fn mesh(&self, x: f32, y: f32, width: f32, height: f32, radius: f32) -> Mesh<Vertex> {
+        //Shadow box, moved by offset and grown by spread
+        let (x, y) = (
+            x + self.offset_x - self.spread,
+            y + self.offset_y - self.spread,
+        );
+        let (width, height) = (width + self.spread * 2.0, height + self.spread * 2.0);
+        let radius = if radius > 0.0 {
+            radius + self.spread
+        } else {
+            0.0
+        };
+
+        let distances: Vec<f32> = if self.blur > 0.0 {
+            (0..=BLUR_STEPS)
+                .map(|step| -self.blur + 2.0 * self.blur * step as f32 / BLUR_STEPS as f32)
+                .collect()
+        } else {
+            vec![0.0]
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        //Solid center, triangle fan from the middle
+        let inner_alpha = if self.blur > 0.0 {
+            self.alpha(distances[0])
+        } else {
+            self.color[3]
+        };
+        vertices.push(self.vertex([x + width / 2.0, y + height / 2.0], inner_alpha));
+        let inner = self.contour(x, y, width, height, radius, distances[0]);
+        let count = inner.len() as u16;
+        for point in inner {
+            vertices.push(self.vertex(point, inner_alpha));
+        }
+        for i in 0..count {
+            indices.extend_from_slice(&[0, 1 + i, 1 + (i + 1) % count]);
+        }
+
+        //Blurred rings around the center
+        for distance in distances.iter().skip(1) {
+            let start = vertices.len() as u16 - count;
+            let contour = self.contour(x, y, width, height, radius, *distance);
+            for point in contour {
+                vertices.push(self.vertex(point, self.alpha(*distance)));
+            }
+            let end = start + count;
+            for i in 0..count {
+                let next = (i + 1) % count;
+                indices.extend_from_slice(&[start + i, end + i, end + next]);
+                indices.extend_from_slice(&[start + i, end + next, start + next]);
+            }
+        }
+
+        Mesh { vertices, indices }
+    }
+}
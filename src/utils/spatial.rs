@@ -0,0 +1,296 @@
+use crate::prelude::*;
+use cgmath::Point2;
+use collision::Aabb2;
+use std::collections::HashMap;
+
+//Cells per axis an entry may cover, larger entries are kept in a separate list
+const MAX_CELL_SPAN: i64 = 64;
+
+//Cell range of an entry, None for oversized entries
+type Cells = Option<(i32, i32, i32, i32)>;
+
+#[derive(Clone, Debug)]
+struct Entry {
+    id: String,
+    bounds: Aabb2<f32>,
+    cells: Cells,
+    order: u64,
+}
+
+/// Uniform grid of element bounds, keyed by element id.
+/// Elements inserted later are on top, like in draw order.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    //Entries spanning too many cells, checked by every query
+    oversized: Vec<usize>,
+    entries: Vec<Option<Entry>>,
+    free: Vec<usize>,
+    ids: HashMap<String, usize>,
+    next_order: u64,
+}
+
+fn overlaps(a: &Aabb2<f32>, b: &Aabb2<f32>) -> bool {
+    a.min.x <= b.max.x && a.max.x >= b.min.x && a.min.y <= b.max.y && a.max.y >= b.min.y
+}
+
+impl SpatialIndex {
+    /// `cell_size` should be around the size of a typical element
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0);
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            ids: HashMap::new(),
+            next_order: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.oversized.clear();
+        self.entries.clear();
+        self.free.clear();
+        self.ids.clear();
+        self.next_order = 0;
+    }
+
+    fn cell_range(&self, bounds: &Aabb2<f32>) -> Cells {
+        let cells = (
+            (bounds.min.x / self.cell_size).floor() as i32,
+            (bounds.min.y / self.cell_size).floor() as i32,
+            (bounds.max.x / self.cell_size).floor() as i32,
+            (bounds.max.y / self.cell_size).floor() as i32,
+        );
+        let span_x = i64::from(cells.2) - i64::from(cells.0);
+        let span_y = i64::from(cells.3) - i64::from(cells.1);
+        if span_x < MAX_CELL_SPAN && span_y < MAX_CELL_SPAN {
+            Some(cells)
+        } else {
+            None
+        }
+    }
+
+    fn link(&mut self, slot: usize, cells: Cells) {
+        let cells = match cells {
+            Some(cells) => cells,
+            None => {
+                self.oversized.push(slot);
+                return;
+            }
+        };
+        for x in cells.0..=cells.2 {
+            for y in cells.1..=cells.3 {
+                self.cells.entry((x, y)).or_insert_with(Vec::new).push(slot);
+            }
+        }
+    }
+
+    fn unlink(&mut self, slot: usize, cells: Cells) {
+        let cells = match cells {
+            Some(cells) => cells,
+            None => {
+                self.oversized.retain(|item| *item != slot);
+                return;
+            }
+        };
+        for x in cells.0..=cells.2 {
+            for y in cells.1..=cells.3 {
+                let empty = match self.cells.get_mut(&(x, y)) {
+                    Some(cell) => {
+                        cell.retain(|item| *item != slot);
+                        cell.is_empty()
+                    }
+                    None => false,
+                };
+                if empty {
+                    self.cells.remove(&(x, y));
+                }
+            }
+        }
+    }
+
+    /// Add element on top of the others, or update it, if the id is already indexed
+    pub fn insert(&mut self, id: &str, bounds: Aabb2<f32>) {
+        if self.ids.contains_key(id) {
+            self.update(id, bounds);
+            return;
+        }
+
+        let cells = self.cell_range(&bounds);
+        let entry = Entry {
+            id: id.to_string(),
+            bounds,
+            cells,
+            order: self.next_order,
+        };
+        self.next_order += 1;
+
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.entries[slot] = Some(entry);
+                slot
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+
+        self.ids.insert(id.to_string(), slot);
+        self.link(slot, cells);
+    }
+
+    /// Move an element, keeps its draw order. Returns false for unknown ids.
+    pub fn update(&mut self, id: &str, bounds: Aabb2<f32>) -> bool {
+        let slot = match self.ids.get(id) {
+            Some(slot) => *slot,
+            None => return false,
+        };
+
+        let cells = self.cell_range(&bounds);
+        let old_cells = self.entries[slot].as_ref().unwrap().cells;
+        if cells != old_cells {
+            self.unlink(slot, old_cells);
+            self.link(slot, cells);
+        }
+
+        let entry = self.entries[slot].as_mut().unwrap();
+        entry.bounds = bounds;
+        entry.cells = cells;
+        true
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let slot = match self.ids.remove(id) {
+            Some(slot) => slot,
+            None => return false,
+        };
+
+        let cells = self.entries[slot].as_ref().unwrap().cells;
+        self.unlink(slot, cells);
+        self.entries[slot] = None;
+        self.free.push(slot);
+        true
+    }
+
+    /// Move element on top of the others
+    pub fn bring_to_front(&mut self, id: &str) {
+        if let Some(slot) = self.ids.get(id) {
+            self.entries[*slot].as_mut().unwrap().order = self.next_order;
+            self.next_order += 1;
+        }
+    }
+
+    pub fn bounds(&self, id: &str) -> Option<Aabb2<f32>> {
+        self.ids
+            .get(id)
+            .map(|slot| self.entries[*slot].as_ref().unwrap().bounds)
+    }
+
+    //Entries from cells, that overlap `area`, every entry only once.
+    //Areas spanning too many cells check every entry instead.
+    fn candidates(&self, area: &Aabb2<f32>) -> Vec<&Entry> {
+        let cells = match self.cell_range(area) {
+            Some(cells) => cells,
+            None => return self.entries.iter().flatten().collect(),
+        };
+        let mut slots: Vec<usize> = self.oversized.clone();
+        for x in cells.0..=cells.2 {
+            for y in cells.1..=cells.3 {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    slots.extend_from_slice(cell);
+                }
+            }
+        }
+        slots.sort();
+        slots.dedup();
+
+        slots
+            .into_iter()
+            .map(|slot| self.entries[slot].as_ref().unwrap())
+            .collect()
+    }
+
+    /// Ids of elements with bounds under the point, topmost first
+    pub fn query_point(&self, point: Point2<f32>) -> Vec<&str> {
+        let area = Aabb2::new(point, point);
+        let mut hits: Vec<&Entry> = self
+            .candidates(&area)
+            .into_iter()
+            .filter(|entry| overlaps(&entry.bounds, &area))
+            .collect();
+
+        hits.sort_by(|a, b| b.order.cmp(&a.order));
+        hits.into_iter().map(|entry| entry.id.as_str()).collect()
+    }
+
+    /// Topmost element with bounds under the point
+    pub fn topmost(&self, point: Point2<f32>) -> Option<&str> {
+        self.query_point(point).into_iter().next()
+    }
+
+    /// Topmost element under the point, `contains` does the exact check,
+    /// for example with `ElementCollider::contains`.
+    pub fn topmost_by<F>(&self, point: Point2<f32>, mut contains: F) -> Option<&str>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.query_point(point).into_iter().find(|id| contains(id))
+    }
+
+    /// Ids of elements, whose bounds overlap `area`, in draw order
+    pub fn query_rect(&self, area: &Aabb2<f32>) -> Vec<&str> {
+        let mut hits: Vec<&Entry> = self
+            .candidates(area)
+            .into_iter()
+            .filter(|entry| overlaps(&entry.bounds, area))
+            .collect();
+
+        hits.sort_by(|a, b| a.order.cmp(&b.order));
+        hits.into_iter().map(|entry| entry.id.as_str()).collect()
+    }
+
+    /// Ids of elements overlapping the element `id`, without itself
+    pub fn query_overlapping(&self, id: &str) -> Vec<&str> {
+        match self.bounds(id) {
+            Some(bounds) => self
+                .query_rect(&bounds)
+                .into_iter()
+                .filter(|other| *other != id)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Index element by `get_id` and `get_collider`, elements without id are skipped
+    pub fn insert_element<E: ElementCore + ElementCollider>(&mut self, element: &E) -> bool {
+        match element.get_id() {
+            Some(id) => {
+                self.insert(&id, element.get_collider());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Update element bounds after its Style changed
+    pub fn update_element<E: ElementCore + ElementCollider>(&mut self, element: &E) -> bool {
+        match element.get_id() {
+            Some(id) => self.update(&id, element.get_collider()),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Texture sub-rectangle in normalized 0..1 coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+}
+
+impl UvRect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Rectangle in texels, on a texture with given size
+    pub fn from_texels(
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        texture_width: f32,
+        texture_height: f32,
+    ) -> Self {
+        Self {
+            x: x / texture_width,
+            y: y / texture_height,
+            width: width / texture_width,
+            height: height / texture_height,
+        }
+    }
+
+    /// Horizontal texture range, swapped when flipped
+    pub fn u_range(&self, flip: bool) -> (f32, f32) {
+        if flip {
+            (self.x + self.width, self.x)
+        } else {
+            (self.x, self.x + self.width)
+        }
+    }
+
+    /// Vertical texture range, swapped when flipped
+    pub fn v_range(&self, flip: bool) -> (f32, f32) {
+        if flip {
+            (self.y + self.height, self.y)
+        } else {
+            (self.y, self.y + self.height)
+        }
+    }
+}
+
+/// One sprite: texture array layer, its area on the layer and size in texels
+#[derive(Copy, Clone, Debug)]
+pub struct Frame {
+    pub layer: i32,
+    pub uv: UvRect,
+    /// Size before trimming
+    pub width: f32,
+    pub height: f32,
+    /// Part of the untrimmed sprite the texture area covers, in 0..1 of its size
+    pub trim: UvRect,
+}
+
+/// Texture array layer sliced into frames, by index or name
+#[derive(Clone, Debug)]
+pub struct SpriteSheet {
+    pub layer: i32,
+    pub texture_width: f32,
+    pub texture_height: f32,
+    pub frames: Vec<Frame>,
+    names: HashMap<String, usize>,
+}
+
+//TexturePacker JSON, "hash" and "array" formats
+#[derive(Copy, Clone, Deserialize)]
+struct AtlasRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Copy, Clone, Deserialize)]
+struct AtlasSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct AtlasFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: AtlasRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    //Trimmed area in the untrimmed sprite
+    #[serde(rename = "spriteSourceSize")]
+    sprite_source_size: Option<AtlasRect>,
+    #[serde(rename = "sourceSize")]
+    source_size: Option<AtlasSize>,
+}
+
+//Hash frames in file order, a map would sort them by name
+struct HashFrames(Vec<(String, AtlasFrame)>);
+
+impl<'de> Deserialize<'de> for HashFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = HashFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<HashFrames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(frame) = map.next_entry()? {
+                    frames.push(frame);
+                }
+                Ok(HashFrames(frames))
+            }
+        }
+
+        deserializer.deserialize_map(FramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AtlasFrames {
+    Hash(HashFrames),
+    Array(Vec<AtlasFrame>),
+}
+
+#[derive(Deserialize)]
+struct AtlasMeta {
+    size: AtlasSize,
+}
+
+#[derive(Deserialize)]
+struct AtlasFile {
+    frames: AtlasFrames,
+    meta: AtlasMeta,
+}
+
+impl SpriteSheet {
+    pub fn new(layer: i32, texture_width: f32, texture_height: f32) -> Self {
+        Self {
+            layer,
+            texture_width,
+            texture_height,
+            frames: Vec::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Add frame in texels, returns its index
+    pub fn add_frame(
+        &mut self,
+        name: Option<&str>,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> usize {
+        let (texture_width, texture_height) = (self.texture_width, self.texture_height);
+        self.frames.push(Frame {
+            layer: self.layer,
+            uv: UvRect::from_texels(x, y, width, height, texture_width, texture_height),
+            width,
+            height,
+            trim: UvRect::default(),
+        });
+
+        let index = self.frames.len() - 1;
+        if let Some(name) = name {
+            self.names.insert(name.to_string(), index);
+        }
+        index
+    }
+
+    /// Slice layer to equal frames, row by row from top left.
+    /// `spacing` is the gap between frames, `margin` the border around the grid, both in texels.
+    pub fn from_grid(
+        layer: i32,
+        texture_width: f32,
+        texture_height: f32,
+        frame_width: f32,
+        frame_height: f32,
+        spacing: f32,
+        margin: f32,
+    ) -> Self {
+        let mut sheet = SpriteSheet::new(layer, texture_width, texture_height);
+
+        let mut y = margin;
+        while y + frame_height <= texture_height - margin {
+            let mut x = margin;
+            while x + frame_width <= texture_width - margin {
+                sheet.add_frame(None, x, y, frame_width, frame_height);
+                x += frame_width + spacing;
+            }
+            y += frame_height + spacing;
+        }
+
+        sheet
+    }
+
+    /// Read TexturePacker JSON, in "hash" or "array" format.
+    /// Frames are indexed in file order, trimmed frames keep their untrimmed size and offset.
+    /// Rotated frames are an error, export the atlas without rotation.
+    pub fn from_json(layer: i32, json: &str) -> Result<Self, serde_json::Error> {
+        let atlas: AtlasFile = serde_json::from_str(json)?;
+        let mut sheet = SpriteSheet::new(layer, atlas.meta.size.w, atlas.meta.size.h);
+
+        let frames: Vec<(Option<String>, AtlasFrame)> = match atlas.frames {
+            AtlasFrames::Hash(frames) => frames
+                .0
+                .into_iter()
+                .map(|(name, frame)| (Some(name), frame))
+                .collect(),
+            AtlasFrames::Array(frames) => frames
+                .into_iter()
+                .map(|mut frame| (frame.filename.take(), frame))
+                .collect(),
+        };
+
+        for (name, frame) in frames {
+            let name = name.as_ref().map(|name| name.as_str());
+            if frame.rotated {
+                return Err(serde::de::Error::custom(format!(
+                    "Frame {} is rotated, export the atlas without rotation",
+                    name.unwrap_or("without a name")
+                )));
+            }
+
+            let rect = frame.frame;
+            let index = sheet.add_frame(name, rect.x, rect.y, rect.w, rect.h);
+            if let (true, Some(offset), Some(source)) =
+                (frame.trimmed, frame.sprite_source_size, frame.source_size)
+            {
+                let sprite = &mut sheet.frames[index];
+                sprite.trim =
+                    UvRect::from_texels(offset.x, offset.y, rect.w, rect.h, source.w, source.h);
+                sprite.width = source.w;
+                sprite.height = source.h;
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Frame> {
+        self.names.get(name).and_then(|index| self.frames.get(*index))
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_frames_are_an_error() {
+        let json = r#"{
+            "frames": {
+                "walk_1": {"frame": {"x": 0, "y": 0, "w": 16, "h": 32}, "rotated": true}
+            },
+            "meta": {"size": {"w": 64, "h": 64}}
+        }"#;
+
+        let error = SpriteSheet::from_json(0, json).unwrap_err();
+        assert!(error.to_string().contains("walk_1"));
+    }
+
+    #[test]
+    fn trimmed_frames_keep_their_offset() {
+        let json = r#"{
+            "frames": [
+                {
+                    "filename": "jump",
+                    "frame": {"x": 32, "y": 0, "w": 20, "h": 30},
+                    "rotated": false,
+                    "trimmed": true,
+                    "spriteSourceSize": {"x": 6, "y": 2, "w": 20, "h": 30},
+                    "sourceSize": {"w": 40, "h": 40}
+                },
+                {
+                    "filename": "idle",
+                    "frame": {"x": 0, "y": 0, "w": 32, "h": 32},
+                    "trimmed": false,
+                    "spriteSourceSize": {"x": 0, "y": 0, "w": 32, "h": 32},
+                    "sourceSize": {"w": 32, "h": 32}
+                }
+            ],
+            "meta": {"size": {"w": 64, "h": 64}}
+        }"#;
+
+        let sheet = SpriteSheet::from_json(0, json).unwrap();
+        let jump = sheet.named("jump").unwrap();
+        assert_eq!(jump.uv, UvRect::new(0.5, 0.0, 20.0 / 64.0, 30.0 / 64.0));
+        assert_eq!((jump.width, jump.height), (40.0, 40.0));
+        assert_eq!(jump.trim, UvRect::new(0.15, 0.05, 0.5, 0.75));
+
+        let idle = sheet.named("idle").unwrap();
+        assert_eq!((idle.width, idle.height), (32.0, 32.0));
+        assert_eq!(idle.trim, UvRect::default());
+    }
+}
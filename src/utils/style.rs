@@ -1,3 +1,7 @@
+use crate::utils::shadow::BoxShadow;
+use crate::utils::transform::Transform;
+use collision::Aabb2;
+
 #[derive(Copy, Clone, Debug)]
 pub enum Direction {
     Diagonal,
@@ -31,6 +35,8 @@ pub struct Style {
     pub y: f32,
     pub radius: f32,
     pub margin: Margin,
+    pub box_shadow: Option<BoxShadow>,
+    pub transform: Transform,
     pub direction: Direction,
     pub row_gap: f32,
     pub column_gap: f32,
@@ -52,6 +58,8 @@ impl Default for Style {
             y: 0.0,
             radius: 0.0,
             margin: Margin::default(),
+            box_shadow: None,
+            transform: Transform::default(),
             direction: Direction::Horizontal,
             row_gap: 0.0,
             column_gap: 0.0,
@@ -67,6 +75,14 @@ impl Default for Style {
 }
 
 impl Style {
+    /// Untransformed box from position and size
+    pub fn bounds(&self) -> Aabb2<f32> {
+        Aabb2::new(
+            cgmath::Point2::new(self.x, self.y),
+            cgmath::Point2::new(self.x + self.width, self.y + self.height),
+        )
+    }
+
     pub fn calculate_style(parent: Style, child: Style) -> Style {
         let single_column = parent.width as usize / parent.columns;
         let single_row = parent.height as usize / parent.rows;
@@ -0,0 +1,136 @@
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FilterMode {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AddressMode {
+    Clamp,
+    Repeat,
+    Mirror,
+}
+
+/// Sampler and format settings of one texture array
+#[derive(Copy, Clone, Debug)]
+pub struct TextureOptions {
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub address_mode: AddressMode,
+    /// Decode texels from sRGB to linear when sampling.
+    /// Swap chain is not sRGB, so leave it off unless the shader output is converted back.
+    pub srgb: bool,
+    /// Generate the full mip chain on upload, sampled with `mipmap_filter`
+    pub mipmaps: bool,
+    pub mipmap_filter: FilterMode,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Linear,
+            address_mode: AddressMode::Clamp,
+            srgb: false,
+            mipmaps: false,
+            mipmap_filter: FilterMode::Linear,
+        }
+    }
+}
+
+impl FilterMode {
+    pub fn to_wgpu(self) -> wgpu::FilterMode {
+        match self {
+            FilterMode::Nearest => wgpu::FilterMode::Nearest,
+            FilterMode::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+impl AddressMode {
+    pub fn to_wgpu(self) -> wgpu::AddressMode {
+        match self {
+            AddressMode::Clamp => wgpu::AddressMode::ClampToEdge,
+            AddressMode::Repeat => wgpu::AddressMode::Repeat,
+            AddressMode::Mirror => wgpu::AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Same filter for magnification and minification
+    pub fn filter(filter: FilterMode) -> Self {
+        Self {
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        }
+    }
+
+    /// Smooth filtering with mipmaps, for downscaled photos
+    pub fn mipmapped() -> Self {
+        Self {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmaps: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+
+    pub fn sampler(&self) -> wgpu::SamplerDescriptor {
+        let address_mode = self.address_mode.to_wgpu();
+        wgpu::SamplerDescriptor {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: self.mag_filter.to_wgpu(),
+            min_filter: self.min_filter.to_wgpu(),
+            mipmap_filter: if self.mipmaps {
+                self.mipmap_filter.to_wgpu()
+            } else {
+                wgpu::FilterMode::Nearest
+            },
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            compare_function: wgpu::CompareFunction::Always,
+        }
+    }
+}
+
+/// Number of levels in a full mip chain, down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Full mip chain of an RGBA image, first level is the image itself.
+/// Every level is downsampled from the previous one with a box-like triangle filter.
+pub fn mip_chain(width: u32, height: u32, data: Vec<u8>) -> Vec<(u32, u32, Vec<u8>)> {
+    let mut levels = Vec::new();
+    let mut image = image::RgbaImage::from_raw(width, height, data)
+        .expect("Raw texture must be RGBA with 4 bytes per pixel");
+
+    for level in 1..mip_level_count(width, height) {
+        let (level_width, level_height) = ((width >> level).max(1), (height >> level).max(1));
+        let next = image::imageops::resize(
+            &image,
+            level_width,
+            level_height,
+            image::imageops::FilterType::Triangle,
+        );
+        let (image_width, image_height) = image.dimensions();
+        levels.push((image_width, image_height, image.into_raw()));
+        image = next;
+    }
+
+    let (image_width, image_height) = image.dimensions();
+    levels.push((image_width, image_height, image.into_raw()));
+    levels
+}
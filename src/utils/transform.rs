@@ -0,0 +1,83 @@
+use crate::definitions::Vertex;
+use cgmath::{Matrix3, Point2, Vector3};
+use collision::Aabb2;
+
+/// Element transform, applied to the built vertices.
+/// Angles are in degrees, pivot is relative to the element box (0.5, 0.5 is the middle).
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub skew_x: f32,
+    pub skew_y: f32,
+    pub pivot_x: f32,
+    pub pivot_y: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            skew_x: 0.0,
+            skew_y: 0.0,
+            pivot_x: 0.5,
+            pivot_y: 0.5,
+        }
+    }
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        self.rotation == 0.0
+            && self.scale_x == 1.0
+            && self.scale_y == 1.0
+            && self.skew_x == 0.0
+            && self.skew_y == 0.0
+    }
+
+    /// Transform matrix around the pivot of `bounds`,
+    /// scale is applied first, then skew and rotation, like CSS `rotate() skew() scale()`.
+    pub fn matrix(&self, bounds: &Aabb2<f32>) -> Matrix3<f32> {
+        let pivot = Point2::new(
+            bounds.min.x + (bounds.max.x - bounds.min.x) * self.pivot_x,
+            bounds.min.y + (bounds.max.y - bounds.min.y) * self.pivot_y,
+        );
+
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (skew_x, skew_y) = (self.skew_x.to_radians().tan(), self.skew_y.to_radians().tan());
+
+        //Linear part: rotation * skew * scale
+        let a = (cos - sin * skew_y) * self.scale_x;
+        let b = (sin + cos * skew_y) * self.scale_x;
+        let c = (cos * skew_x - sin) * self.scale_y;
+        let d = (sin * skew_x + cos) * self.scale_y;
+
+        //Keep the pivot in place
+        let tx = pivot.x - (a * pivot.x + c * pivot.y);
+        let ty = pivot.y - (b * pivot.x + d * pivot.y);
+
+        Matrix3::new(a, b, 0.0, c, d, 0.0, tx, ty, 1.0)
+    }
+
+    pub fn transform_point(matrix: &Matrix3<f32>, point: Point2<f32>) -> Point2<f32> {
+        let result = matrix * Vector3::new(point.x, point.y, 1.0);
+        Point2::new(result.x, result.y)
+    }
+
+    /// Move built vertices, `bounds` is the untransformed element box.
+    pub fn apply(&self, bounds: &Aabb2<f32>, vertices: &mut [Vertex]) {
+        if self.is_identity() {
+            return;
+        }
+
+        let matrix = self.matrix(bounds);
+        for vertex in vertices.iter_mut() {
+            let point = Point2::new(vertex.in_position[0], vertex.in_position[1]);
+            let point = Transform::transform_point(&matrix, point);
+            vertex.in_position = [point.x, point.y];
+        }
+    }
+}